@@ -1,24 +1,472 @@
-use crate::init::ACTIVITIES_FILE;
+use crate::rrule::Rrule;
+use crate::storage::Storage;
 use crate::time_utils;
-use bincode;
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, Weekday};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
-use std::fs::File;
-use std::io::{self, ErrorKind};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
+// new variants go after `Invalid`, bincode stores the variant index
 #[derive(Debug, Eq, PartialEq, Hash, Serialize, Deserialize, Clone)]
 pub enum FrequencyType {
     AllWeek,
     WorkingDays,
     WeekEnds,
     Invalid,
+    /// Any combination of days, like Monday, Wednesday and Friday.
+    Weekdays(WeekdaySet),
+    /// A number of completions on any days of each ISO week or calendar month.
+    Quota {
+        times: u8,
+        period: QuotaPeriod,
+    },
+    /// Every `days` days, counted from the start or from the last completion.
+    Interval {
+        days: u16,
+        anchor: IntervalAnchor,
+    },
+    /// Once a month, on a day of the month or a weekday of a given week.
+    Monthly(MonthlyRule),
+    /// Any RFC 5545 recurrence rule, like `FREQ=WEEKLY;INTERVAL=2;BYDAY=TU`, see [`Rrule`].
+    Rrule(String),
 }
 
-#[derive(Debug, Eq, PartialEq, Serialize, Deserialize, Clone)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum MonthlyRule {
+    /// Day of the month. Days a month doesn't have fall on its last day, so the 31st is
+    /// the end of every month.
+    Day(u8),
+    /// The `nth` (1 to 5) given weekday of the month, skipped in months without a 5th one.
+    Weekday { nth: u8, weekday: Weekday },
+    /// The last given weekday of the month.
+    LastWeekday(Weekday),
+}
+
+impl MonthlyRule {
+    pub fn matches(&self, date: NaiveDate) -> bool {
+        match *self {
+            MonthlyRule::Day(day) => date.day() == u32::from(day.min(date.num_days_in_month())),
+            MonthlyRule::Weekday { nth, weekday } => {
+                date.weekday() == weekday && (date.day() - 1) / 7 + 1 == u32::from(nth)
+            }
+            MonthlyRule::LastWeekday(weekday) => {
+                date.weekday() == weekday && date.day() + 7 > u32::from(date.num_days_in_month())
+            }
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        match self {
+            MonthlyRule::Day(day) => (1..=31).contains(day),
+            MonthlyRule::Weekday { nth, .. } => (1..=5).contains(nth),
+            MonthlyRule::LastWeekday(_) => true,
+        }
+    }
+
+    fn code(&self) -> String {
+        let day = |weekday: &Weekday| weekday.to_string().to_uppercase();
+        match self {
+            MonthlyRule::Day(day) => ordinal(*day),
+            MonthlyRule::Weekday { nth, weekday } => format!("{} {}", ordinal(*nth), day(weekday)),
+            MonthlyRule::LastWeekday(weekday) => format!("LAST {}", day(weekday)),
+        }
+    }
+
+    /// Parses `15TH`, `2ND TUE` or `LAST SUN`.
+    fn from_code(code: &str) -> Option<MonthlyRule> {
+        let mut words = code.split_whitespace();
+        let (nth, weekday) = (words.next()?, words.next());
+        if words.next().is_some() {
+            return None;
+        }
+        let weekday = weekday.map(str::parse::<Weekday>).transpose().ok()?;
+        if nth == "LAST" {
+            return weekday.map(MonthlyRule::LastWeekday);
+        }
+        let number = nth
+            .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+            .parse()
+            .ok()?;
+        if ordinal(number) != nth {
+            return None;
+        }
+        let nth = number;
+        Some(match weekday {
+            Some(weekday) => MonthlyRule::Weekday { nth, weekday },
+            None => MonthlyRule::Day(nth),
+        })
+    }
+}
+
+/// `1ST`, `2ND`, `3RD`, `4TH`, ..., `11TH`, ..., `21ST`.
+fn ordinal(n: u8) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "TH",
+        (1, _) => "ST",
+        (2, _) => "ND",
+        (3, _) => "RD",
+        _ => "TH",
+    };
+    format!("{}{}", n, suffix)
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum IntervalAnchor {
+    /// Due on the start day and every `days` days after it, done or not.
+    Start,
+    /// Due `days` days after the last completion, and every day after that until done.
+    LastCompletion,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum QuotaPeriod {
+    Week,
+    Month,
+}
+
+impl QuotaPeriod {
+    /// First day of the period `date` is in.
+    pub fn start_of(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            QuotaPeriod::Week => date.week(Weekday::Mon).first_day(),
+            QuotaPeriod::Month => date.with_day(1).expect("Invalid date"),
+        }
+    }
+
+    /// Most days a period can have, which is the highest possible quota.
+    fn max_days(&self) -> u8 {
+        match self {
+            QuotaPeriod::Week => 7,
+            QuotaPeriod::Month => 31,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            QuotaPeriod::Week => "W",
+            QuotaPeriod::Month => "M",
+        }
+    }
+}
+
+/// Set of weekdays, one bit per day starting with Monday.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct WeekdaySet(u8);
+
+impl WeekdaySet {
+    pub fn new<I: IntoIterator<Item = Weekday>>(days: I) -> Self {
+        let mut set = WeekdaySet::default();
+        for day in days {
+            set.0 |= 1 << day.num_days_from_monday();
+        }
+        set
+    }
+
+    pub fn contains(&self, day: Weekday) -> bool {
+        self.0 & (1 << day.num_days_from_monday()) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Days of the set, from Monday to Sunday.
+    pub fn days(&self) -> Vec<Weekday> {
+        (0..7)
+            .map(|n| Weekday::try_from(n).expect("Invalid weekday"))
+            .filter(|day| self.contains(*day))
+            .collect()
+    }
+}
+
+impl FrequencyType {
+    /// Short code of the frequency, as typed in the TUI and stored in text formats.
+    pub fn code(&self) -> String {
+        match self {
+            FrequencyType::AllWeek => String::from("AW"),
+            FrequencyType::WorkingDays => String::from("WD"),
+            FrequencyType::WeekEnds => String::from("WE"),
+            FrequencyType::Invalid => String::new(),
+            FrequencyType::Weekdays(set) => set
+                .days()
+                .iter()
+                .map(|day| day.to_string().to_uppercase())
+                .collect::<Vec<_>>()
+                .join(","),
+            FrequencyType::Quota { times, period } => format!("{}/{}", times, period.code()),
+            FrequencyType::Interval { days, anchor } => match anchor {
+                IntervalAnchor::Start => format!("{}D", days),
+                IntervalAnchor::LastCompletion => format!("{}D/L", days),
+            },
+            FrequencyType::Monthly(rule) => rule.code(),
+            FrequencyType::Rrule(rule) => format!("RRULE:{}", rule),
+        }
+    }
+
+    /// Parses a code made by [`FrequencyType::code`], unknown codes are `Invalid`.
+    ///
+    /// Weekday sets are day names separated by commas or spaces, like `MON,WED,FRI`, and
+    /// quotas are a number of times per week or month, like `3/W` or `2/M`. Intervals are a
+    /// number of days, like `3D` from the start or `3D/L` from the last completion. Monthly
+    /// rules are a day of the month like `1ST`, or a weekday of the month like `LAST SUN`,
+    /// and recurrence rules start with `RRULE:`.
+    pub fn from_code(code: &str) -> FrequencyType {
+        let code = code.trim().to_uppercase();
+        if let Some(rule) = code.strip_prefix("RRULE:") {
+            return FrequencyType::Rrule(rule.trim().to_string()).validated();
+        }
+        if let Some(interval) = Self::interval_from_code(&code) {
+            return interval;
+        }
+        if let Some(rule) = MonthlyRule::from_code(&code) {
+            return FrequencyType::Monthly(rule).validated();
+        }
+        match code.as_str() {
+            "AW" => FrequencyType::AllWeek,
+            "WD" => FrequencyType::WorkingDays,
+            "WE" => FrequencyType::WeekEnds,
+            quota if quota.contains('/') => {
+                let (times, period) = quota.split_once('/').unwrap_or_default();
+                let period = match period.trim() {
+                    "W" | "WEEK" => QuotaPeriod::Week,
+                    "M" | "MONTH" => QuotaPeriod::Month,
+                    _ => return FrequencyType::Invalid,
+                };
+                match times.trim().parse() {
+                    Ok(times) => FrequencyType::Quota { times, period }.validated(),
+                    Err(_) => FrequencyType::Invalid,
+                }
+            }
+            days => {
+                let days: Result<Vec<Weekday>, _> = days
+                    .split([',', ' '])
+                    .filter(|day| !day.is_empty())
+                    .map(str::parse)
+                    .collect();
+                match days.map(WeekdaySet::new) {
+                    Ok(set) if !set.is_empty() => FrequencyType::Weekdays(set),
+                    _ => FrequencyType::Invalid,
+                }
+            }
+        }
+    }
+
+    fn interval_from_code(code: &str) -> Option<FrequencyType> {
+        let (days, anchor) = match code.split_once('/') {
+            Some((days, "L" | "LAST")) => (days, IntervalAnchor::LastCompletion),
+            Some(_) => return None,
+            None => (code, IntervalAnchor::Start),
+        };
+        let days = days.trim().strip_suffix('D')?.trim().parse().ok()?;
+        Some(FrequencyType::Interval { days, anchor }.validated())
+    }
+
+    /// True unless the frequency can never be due, like an empty weekday set.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            FrequencyType::Invalid => false,
+            FrequencyType::Weekdays(set) => !set.is_empty(),
+            FrequencyType::Quota { times, period } => (1..=period.max_days()).contains(times),
+            FrequencyType::Interval { days, .. } => *days > 0,
+            FrequencyType::Monthly(rule) => rule.is_valid(),
+            FrequencyType::Rrule(rule) => rule.parse::<Rrule>().is_ok(),
+            FrequencyType::AllWeek | FrequencyType::WorkingDays | FrequencyType::WeekEnds => true,
+        }
+    }
+
+    fn validated(self) -> FrequencyType {
+        match self.is_valid() {
+            true => self,
+            false => FrequencyType::Invalid,
+        }
+    }
+
+    /// True if activities with this frequency can be due on `day`.
+    ///
+    /// Quotas, intervals, monthly and recurrence rules only fall on some of these days, see
+    /// [`ActivityDetails::is_due_on`] for when they are due.
+    pub fn is_scheduled_on(&self, day: Weekday) -> bool {
+        let weekend = matches!(day, Weekday::Sat | Weekday::Sun);
+        match self {
+            FrequencyType::AllWeek => true,
+            FrequencyType::WorkingDays => !weekend,
+            FrequencyType::WeekEnds => weekend,
+            FrequencyType::Invalid => false,
+            FrequencyType::Weekdays(set) => set.contains(day),
+            FrequencyType::Quota { .. }
+            | FrequencyType::Interval { .. }
+            | FrequencyType::Rrule(_) => true,
+            FrequencyType::Monthly(MonthlyRule::Day(_)) => true,
+            FrequencyType::Monthly(
+                MonthlyRule::Weekday { weekday, .. } | MonthlyRule::LastWeekday(weekday),
+            ) => day == *weekday,
+        }
+    }
+}
+
+/// Whether an activity is something to do, or something to avoid.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivityKind {
+    #[default]
+    Do,
+    /// Done unless a slip is logged, its completions being the slips.
+    Avoid,
+}
+
+impl ActivityKind {
+    /// `do` or `avoid`, as in text formats.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ActivityKind::Do => "do",
+            ActivityKind::Avoid => "avoid",
+        }
+    }
+}
+
+/// Amount to reach on each scheduled day, like 2 km or 8 glasses.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Target {
+    pub amount: f64,
+    /// Lowercase, empty for plain counts.
+    pub unit: String,
+}
+
+impl Target {
+    /// Reads targets like `2 km`, `8 glasses`, `30min` or `10`.
+    pub fn from_code(code: &str) -> Option<Target> {
+        let code = code.trim();
+        let split = code
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(code.len());
+        let (amount, unit) = code.split_at(split);
+        let amount = parse_amount(amount)?;
+        Some(Target {
+            amount,
+            unit: unit.trim().to_lowercase(),
+        })
+    }
+
+    pub fn code(&self) -> String {
+        self.progress(None)
+    }
+
+    /// `1.2/2 km` for `done` 1.2, `2 km` without `done`.
+    pub fn progress(&self, done: Option<f64>) -> String {
+        let amount = match done {
+            Some(done) => format!("{}/{}", format_amount(done), format_amount(self.amount)),
+            None => format_amount(self.amount),
+        };
+        match self.unit.is_empty() {
+            true => amount,
+            false => format!("{} {}", amount, self.unit),
+        }
+    }
+}
+
+/// Positive, finite amount, like `1.2`.
+pub fn parse_amount(text: &str) -> Option<f64> {
+    text.trim()
+        .parse()
+        .ok()
+        .filter(|amount: &f64| amount.is_finite() && *amount > 0.0)
+}
+
+/// Amount with at most two decimals and no trailing zeros.
+pub fn format_amount(amount: f64) -> String {
+    let text = format!("{:.2}", amount);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Days, both included, when an activity or the whole tracker is paused, like a holiday.
+/// Paused days count neither for nor against perfect days.
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize, Clone, Copy)]
+pub struct Pause {
+    pub from: NaiveDate,
+    pub until: NaiveDate,
+}
+
+impl Pause {
+    /// Reads ranges like `2024-01-20..2024-01-27`, or a last day like `2024-01-27` for a
+    /// pause starting on `today`.
+    pub fn from_code(code: &str, today: NaiveDate) -> Option<Pause> {
+        let date = |text: &str| NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d").ok();
+        let (from, until) = match code.split_once("..") {
+            Some((from, until)) => (date(from)?, date(until)?),
+            None => (today, date(code)?),
+        };
+        (from <= until).then_some(Pause { from, until })
+    }
+
+    pub fn code(&self) -> String {
+        format!("{}..{}", self.from, self.until)
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        (self.from..=self.until).contains(&date)
+    }
+}
+
+/// Local day a completion counts for, and the UTC offset of the time it was logged at.
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize, Clone, Copy)]
+pub struct LocalDay {
+    pub date: NaiveDate,
+    /// Seconds east of UTC.
+    pub offset: i32,
+}
+
+impl LocalDay {
+    /// Day of a completion logged now, or at `timestamp` in the current time zone.
+    pub fn of(timestamp: i64) -> LocalDay {
+        let time = time_utils::local_time(timestamp);
+        LocalDay {
+            date: time_utils::day_of(&time, time_utils::day_start()),
+            offset: time.offset().local_minus_utc(),
+        }
+    }
+
+    /// Time of `timestamp` with the UTC offset it was logged at.
+    pub fn time_of(&self, timestamp: i64) -> DateTime<FixedOffset> {
+        let offset = FixedOffset::east_opt(self.offset)
+            .unwrap_or(FixedOffset::east_opt(0).expect("Invalid offset"));
+        DateTime::from_timestamp(timestamp, 0)
+            .expect("Invalid timestamp")
+            .with_timezone(&offset)
+    }
+}
+
+/// Ends the pauses going on on `today` the day before, and drops the ones after it.
+fn resume(pauses: &mut Vec<Pause>, today: NaiveDate) {
+    pauses.retain(|pause| pause.from < today);
+    for pause in pauses.iter_mut() {
+        pause.until = pause.until.min(today.pred_opt().unwrap_or(today));
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct ActivityDetails {
-    start: i64,
-    frequency: FrequencyType,
-    completion_timestamps: HashSet<i64>,
+    /// Uppercased, unique among the activities.
+    pub(crate) name: String,
+    pub(crate) start: i64,
+    pub(crate) frequency: FrequencyType,
+    pub(crate) completion_timestamps: HashSet<i64>,
+    /// Set for quantitative activities, which are done once the amounts logged on a day
+    /// reach it.
+    pub(crate) target: Option<Target>,
+    /// Amounts logged towards the target, by time.
+    pub(crate) progress: BTreeMap<i64, f64>,
+    pub(crate) kind: ActivityKind,
+    pub(crate) pauses: Vec<Pause>,
+    /// Days the activity was skipped, like for an injury or rain, with the reason. They
+    /// count neither for nor against perfect days.
+    pub(crate) skips: BTreeMap<NaiveDate, String>,
+    /// Day each completion was logged for, by timestamp. Completions without one, like
+    /// imported ones, count for the local day of their timestamp.
+    pub(crate) completion_days: HashMap<i64, LocalDay>,
+    /// Day the activity was removed on. Removed activities are kept with their history so
+    /// that the days before stay as they were.
+    pub(crate) removed: Option<NaiveDate>,
+    /// Day each progress entry was logged for, by timestamp, like the completion days.
+    pub(crate) progress_days: HashMap<i64, LocalDay>,
 }
 
 impl ActivityDetails {
@@ -27,63 +475,324 @@ impl ActivityDetails {
         //let figures: HashSet<i64> = vec![1706117517 , 1705771917 ].into_iter().collect();
 
         ActivityDetails {
+            name: String::new(),
             start: time_utils::current_time_timestamp(),
             frequency,
             completion_timestamps: HashSet::new(),
+            target: None,
+            progress: BTreeMap::new(),
+            kind: ActivityKind::Do,
+            pauses: Vec::new(),
+            skips: BTreeMap::new(),
+            completion_days: HashMap::new(),
+            removed: None,
+            progress_days: HashMap::new(),
+        }
+    }
+
+    pub fn with_kind(mut self, kind: ActivityKind) -> ActivityDetails {
+        self.kind = kind;
+        self
+    }
+
+    /// Checks that the frequency is valid and that activities to avoid have fixed days,
+    /// as quotas and intervals following the last completion count completions, and no
+    /// target.
+    pub fn check(&self) -> Result<(), &'static str> {
+        let counts_completions = matches!(
+            self.frequency,
+            FrequencyType::Quota { .. }
+                | FrequencyType::Interval {
+                    anchor: IntervalAnchor::LastCompletion,
+                    ..
+                }
+        );
+        match (self.kind, self.frequency.is_valid()) {
+            (_, false) => Err("Wrong Frequency Type"),
+            (ActivityKind::Avoid, true) if counts_completions => {
+                Err("Activities To Avoid Need Fixed Days")
+            }
+            (ActivityKind::Avoid, true) if self.target.is_some() => {
+                Err("Activities To Avoid Have No Target")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn with_target(mut self, target: Option<Target>) -> ActivityDetails {
+        self.target = target;
+        self
+    }
+
+    /// True if the activity is on the list of `date`. Quotas stay on it until they are met,
+    /// counting the completions of the period before `date`, and intervals anchored at the
+    /// last completion stay on it until done once they are due.
+    pub fn is_due_on(&self, date: NaiveDate) -> bool {
+        match &self.frequency {
+            FrequencyType::Quota { times, period } => {
+                self.is_done_on(date)
+                    || self.days_done_between(period.start_of(date), date) < *times as usize
+            }
+            FrequencyType::Interval {
+                days,
+                anchor: IntervalAnchor::Start,
+            } => {
+                (date - time_utils::local_date(self.start))
+                    .num_days()
+                    .rem_euclid(*days as i64)
+                    == 0
+            }
+            FrequencyType::Interval {
+                days,
+                anchor: IntervalAnchor::LastCompletion,
+            } => {
+                let last_done = self.completion_dates().filter(|done| *done < date).max();
+                match last_done {
+                    Some(last_done) => {
+                        self.is_done_on(date) || (date - last_done).num_days() >= *days as i64
+                    }
+                    None => date >= time_utils::local_date(self.start),
+                }
+            }
+            FrequencyType::Monthly(rule) => rule.matches(date),
+            FrequencyType::Rrule(rule) => rule
+                .parse::<Rrule>()
+                .is_ok_and(|rule| rule.occurs_on(time_utils::local_date(self.start), date)),
+            frequency => frequency.is_scheduled_on(date.weekday()),
+        }
+    }
+
+    pub fn is_paused_on(&self, date: NaiveDate) -> bool {
+        self.pauses.iter().any(|pause| pause.contains(date))
+    }
+
+    pub fn is_removed(&self) -> bool {
+        self.removed.is_some()
+    }
+
+    /// True from the day the activity started until the day before it was removed.
+    pub fn exists_on(&self, date: NaiveDate) -> bool {
+        date >= time_utils::local_date(self.start)
+            && self.removed.is_none_or(|removed| date < removed)
+    }
+
+    /// True if the activity was done on `date`, or had no slip for activities to avoid.
+    fn is_completed_on(&self, date: NaiveDate) -> bool {
+        match self.kind {
+            ActivityKind::Do => self.is_done_on(date),
+            ActivityKind::Avoid => !self.is_done_on(date),
         }
     }
+
+    pub fn is_done_on(&self, date: NaiveDate) -> bool {
+        self.completion_dates().any(|done| done == date)
+    }
+
+    /// Day the completion at `timestamp` counts for, the one it was logged for if known.
+    pub fn completion_day(&self, timestamp: i64) -> LocalDay {
+        self.completion_days
+            .get(&timestamp)
+            .copied()
+            .unwrap_or_else(|| LocalDay::of(timestamp))
+    }
+
+    pub(crate) fn add_completion(&mut self, timestamp: i64, day: LocalDay) {
+        self.completion_timestamps.insert(timestamp);
+        self.completion_days.insert(timestamp, day);
+    }
+
+    pub(crate) fn remove_completion(&mut self, timestamp: i64) {
+        self.completion_timestamps.remove(&timestamp);
+        self.completion_days.remove(&timestamp);
+    }
+
+    fn completion_dates(&self) -> impl Iterator<Item = NaiveDate> + '_ {
+        self.completion_timestamps
+            .iter()
+            .map(|timestamp| self.completion_day(*timestamp).date)
+    }
+
+    /// Day the progress logged at `timestamp` counts for, the one it was logged for if known.
+    pub fn progress_day(&self, timestamp: i64) -> LocalDay {
+        self.progress_days
+            .get(&timestamp)
+            .copied()
+            .unwrap_or_else(|| LocalDay::of(timestamp))
+    }
+
+    pub(crate) fn add_progress(&mut self, timestamp: i64, amount: f64, day: LocalDay) {
+        *self.progress.entry(timestamp).or_insert(0.0) += amount;
+        self.progress_days.insert(timestamp, day);
+    }
+
+    pub(crate) fn remove_progress(&mut self, timestamp: i64, amount: f64) {
+        if let Some(logged) = self.progress.get_mut(&timestamp) {
+            *logged -= amount;
+            // what is left of an amount taken back whole is only a rounding error
+            if *logged < 1e-9 {
+                self.progress.remove(&timestamp);
+                self.progress_days.remove(&timestamp);
+            }
+        }
+    }
+
+    /// Sum of the amounts logged on `date`.
+    pub fn progress_on(&self, date: NaiveDate) -> f64 {
+        self.progress
+            .iter()
+            .filter(|(timestamp, _)| self.progress_day(**timestamp).date == date)
+            .map(|(_, amount)| amount)
+            .sum()
+    }
+
+    /// True if the amounts logged on `date` reach the target, with some slack for the
+    /// rounding of decimal amounts.
+    pub fn is_target_reached_on(&self, date: NaiveDate) -> bool {
+        self.target
+            .as_ref()
+            .is_some_and(|target| self.progress_on(date) >= target.amount - 1e-9)
+    }
+
+    /// Number of days with a completion from `from` until the day before `until`.
+    fn days_done_between(&self, from: NaiveDate, until: NaiveDate) -> usize {
+        self.completion_dates()
+            .filter(|date| (from..until).contains(date))
+            .collect::<HashSet<_>>()
+            .len()
+    }
+}
+
+/// How an activity went on a day.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActivityStatus {
+    Done,
+    /// Excused, with the reason given.
+    Skipped(String),
+    /// Not done, or not done yet for today.
+    Missed,
 }
 
 #[derive(Debug)]
 pub struct Day {
-    activities: HashMap<String, bool>,
+    activities: HashMap<String, ActivityStatus>,
+    /// Status shown instead of `[-]` for missed activities, like the progress `1.2/2 km`
+    /// of quantitative activities or `[x]` for a slip.
+    labels: HashMap<String, String>,
+    /// Activities paused on that day, left out of the ones above.
+    paused: Vec<String>,
 }
 
 impl Day {
-    pub fn today() -> Day {
-        Self::get_day(0)
+    pub fn today(activities_data: &AllActivities) -> Day {
+        Self::get_day(activities_data, 0)
     }
 
-    pub fn day_types_to_show(adjustment: i64) -> [FrequencyType; 2] {
-        let weekday = time_utils::todays_weekday(adjustment).number_from_monday();
-        match weekday {
-            x if x >= 6 => [FrequencyType::AllWeek, FrequencyType::WeekEnds],
-            _ => [FrequencyType::AllWeek, FrequencyType::WorkingDays],
-        }
+    /// Activities of the day `adjustment` days from today, among the ones that existed then.
+    pub fn get_day(activities_data: &AllActivities, adjustment: i64) -> Day {
+        Self::get_day_from(activities_data, adjustment, None)
     }
 
-    pub fn get_day(adjustment: i64) -> Day {
-        let activities_data: AllActivities =
-            AllActivities::load_from_file().expect("Failed to load activities");
-
-        let mut today_activities: HashMap<String, bool> = HashMap::new();
-        let td = Self::day_types_to_show(adjustment);
+    /// Like [`Day::get_day`], with the ids of the activities completed that day when the
+    /// storage looked them up instead of scanning the completions of every activity.
+    pub fn get_day_from(
+        activities_data: &AllActivities,
+        adjustment: i64,
+        completed: Option<&HashSet<u64>>,
+    ) -> Day {
+        let mut today_activities: HashMap<String, ActivityStatus> = HashMap::new();
+        let mut labels: HashMap<String, String> = HashMap::new();
+        let date = time_utils::adjusted_date(adjustment);
+        let mut paused: Vec<String> = activities_data
+            .activities
+            .values()
+            .filter(|activity| activity.exists_on(date))
+            .filter(|activity| activities_data.is_paused_on(date) || activity.is_paused_on(date))
+            .map(|activity| activity.name.clone())
+            .collect();
+        paused.sort();
 
         activities_data
             .activities
             .iter()
-            .filter(|(_, activity)| td.contains(&activity.frequency))
-            .for_each(|(name, _)| {
-                today_activities.insert(
-                    name.clone(),
-                    activities_data.is_activity_completed_on_day(String::from(name), adjustment),
-                );
+            .filter(|(_, activity)| activity.exists_on(date) && activity.is_due_on(date))
+            .filter(|(_, activity)| !paused.contains(&activity.name))
+            .for_each(|(id, activity)| {
+                let name = &activity.name;
+                let done = match completed {
+                    Some(completed) => match activity.kind {
+                        ActivityKind::Do => completed.contains(id),
+                        ActivityKind::Avoid => !completed.contains(id),
+                    },
+                    None => activity.is_completed_on(date),
+                };
+                match (&activity.target, activity.kind, done) {
+                    (_, _, true) => {}
+                    (_, ActivityKind::Avoid, false) => {
+                        labels.insert(name.clone(), String::from("[x]"));
+                    }
+                    (Some(target), ActivityKind::Do, false) => {
+                        labels.insert(
+                            name.clone(),
+                            target.progress(Some(activity.progress_on(date))),
+                        );
+                    }
+                    (None, ActivityKind::Do, false) => {}
+                }
+                let status = match (done, activity.skips.get(&date)) {
+                    (true, _) => ActivityStatus::Done,
+                    (false, Some(reason)) => ActivityStatus::Skipped(reason.clone()),
+                    (false, None) => ActivityStatus::Missed,
+                };
+                today_activities.insert(name.clone(), status);
             });
 
         Day {
             activities: today_activities,
+            labels,
+            paused,
         }
     }
 
+    /// Names of the activities paused on that day, sorted.
+    pub fn paused_activities(&self) -> &[String] {
+        &self.paused
+    }
+
+    /// True if there is nothing to do because of pauses.
+    pub fn is_paused(&self) -> bool {
+        self.activities.is_empty() && !self.paused.is_empty()
+    }
+
+    /// True if every activity was paused or skipped, such days are left out when counting
+    /// perfect days.
+    pub fn is_excused(&self) -> bool {
+        let skipped = |status: &ActivityStatus| matches!(status, ActivityStatus::Skipped(_));
+        match self.activities.is_empty() {
+            true => self.is_paused(),
+            false => self.activities.values().all(skipped),
+        }
+    }
+
+    /// Number of activities still missed.
+    pub fn remaining(&self) -> usize {
+        self.activities
+            .values()
+            .filter(|status| **status == ActivityStatus::Missed)
+            .count()
+    }
+
     pub fn today_activities_list(&self) -> (Vec<String>, Vec<String>) {
         let mut activities_status_list = Vec::new();
         self.activities.iter().for_each(|(key, value)| {
-            let short_value: &str;
-            match value {
-                true => short_value = "[+]",
-                false => short_value = "[-]",
+            let short_value = match value {
+                ActivityStatus::Done => String::from("[+]"),
+                ActivityStatus::Skipped(reason) => format!("[~] {}", reason),
+                ActivityStatus::Missed => self
+                    .labels
+                    .get(key)
+                    .map_or(String::from("[-]"), String::clone),
             };
-            activities_status_list.push((String::from(key), String::from(short_value)));
+            activities_status_list.push((String::from(key), short_value));
         });
 
         // Sort the vector based on the activities
@@ -102,11 +811,13 @@ impl Day {
         (activities_list, status_list)
     }
 
+    /// True if every activity was done, skipped ones aside, and at least one was.
     pub fn is_perfect_day(&self) -> bool {
-        match self.activities.is_empty() {
-            true => false,
-            false => self.activities.iter().all(|(_, &value)| value),
-        }
+        self.remaining() == 0
+            && self
+                .activities
+                .values()
+                .any(|status| *status == ActivityStatus::Done)
     }
 
     /*
@@ -117,95 +828,391 @@ impl Day {
     */
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 
 pub struct AllActivities {
-    pub activities: HashMap<String, ActivityDetails>,
+    /// Activities by id, which stays the same when they are renamed.
+    pub activities: HashMap<u64, ActivityDetails>,
+    /// Last id given to an activity, ids are never reused.
+    pub last_id: u64,
+    /// Pauses of the whole tracker, on top of the ones of each activity.
+    pub pauses: Vec<Pause>,
 }
 
 impl AllActivities {
     pub fn new() -> Self {
         AllActivities {
             activities: HashMap::new(),
+            last_id: 0,
+            pauses: Vec::new(),
+        }
+    }
+
+    /// Adds `activity` under `name` with a new id, without saving it. Returns the id.
+    pub fn insert(&mut self, name: String, mut activity: ActivityDetails) -> u64 {
+        self.last_id += 1;
+        activity.name = name;
+        self.activities.insert(self.last_id, activity);
+        self.last_id
+    }
+
+    pub fn is_paused_on(&self, date: NaiveDate) -> bool {
+        self.pauses.iter().any(|pause| pause.contains(date))
+    }
+
+    /// Adds a pause to an activity, or to the whole tracker without a name.
+    pub fn pause(
+        &mut self,
+        storage: &mut dyn Storage,
+        activity_name: Option<String>,
+        pause: Pause,
+    ) -> Result<(), &'static str> {
+        let (id, mut pauses) = self.pauses_of(activity_name)?;
+        pauses.push(pause);
+        self.set_pauses(storage, id, pauses)
+    }
+
+    /// Ends the pauses of the tracker and of every activity from today on.
+    pub fn resume_all(&mut self, storage: &mut dyn Storage) -> Result<(), &'static str> {
+        let today = time_utils::current_date();
+        let mut ids: Vec<Option<u64>> = self.activities.keys().copied().map(Some).collect();
+        ids.push(None);
+        for id in ids {
+            let mut pauses = match id {
+                Some(id) => self.activities[&id].pauses.clone(),
+                None => self.pauses.clone(),
+            };
+            let before = pauses.clone();
+            resume(&mut pauses, today);
+            if pauses != before {
+                self.set_pauses(storage, id, pauses)?;
+            }
         }
+        Ok(())
     }
-    pub fn save_to_file(&self) -> io::Result<()> {
-        let file = File::create(ACTIVITIES_FILE)?;
-        bincode::serialize_into(file, self).map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+
+    fn pauses_of(
+        &self,
+        activity_name: Option<String>,
+    ) -> Result<(Option<u64>, Vec<Pause>), &'static str> {
+        match activity_name {
+            Some(name) => {
+                let id = self.id_of(&name).ok_or("Activity Not Found")?;
+                Ok((Some(id), self.activities[&id].pauses.clone()))
+            }
+            None => Ok((None, self.pauses.clone())),
+        }
     }
 
-    pub fn load_from_file() -> Result<Self, io::Error> {
-        match File::open(ACTIVITIES_FILE) {
-            Ok(file) => {
-                bincode::deserialize_from(file).map_err(|e| io::Error::new(ErrorKind::Other, e))
+    /// Changes the pauses to `pauses`, saving each pause removed or added on its own so that
+    /// the pauses added by another instance meanwhile are kept.
+    fn set_pauses(
+        &mut self,
+        storage: &mut dyn Storage,
+        id: Option<u64>,
+        pauses: Vec<Pause>,
+    ) -> Result<(), &'static str> {
+        let before = match id {
+            Some(id) => self
+                .activities
+                .get(&id)
+                .map_or_else(Vec::new, |activity| activity.pauses.clone()),
+            None => self.pauses.clone(),
+        };
+        for pause in before.iter().filter(|pause| !pauses.contains(pause)) {
+            storage
+                .remove_pause(id, pause)
+                .map_err(|_| "Failed to save activities")?;
+        }
+        for pause in pauses.iter().filter(|pause| !before.contains(pause)) {
+            storage
+                .add_pause(id, pause)
+                .map_err(|_| "Failed to save activities")?;
+        }
+        match id {
+            Some(id) => {
+                if let Some(activity) = self.activities.get_mut(&id) {
+                    activity.pauses = pauses;
+                }
             }
-            Err(e) => Err(e),
+            None => self.pauses = pauses,
         }
+        Ok(())
+    }
+
+    /// Activities that were not removed, by id.
+    pub fn current(&self) -> impl Iterator<Item = (&u64, &ActivityDetails)> {
+        self.activities
+            .iter()
+            .filter(|(_, activity)| !activity.is_removed())
+    }
+
+    /// Id of the current activity named `name`, removed ones giving their name up.
+    pub fn id_of(&self, name: &str) -> Option<u64> {
+        self.current()
+            .find(|(_, activity)| activity.name == name)
+            .map(|(id, _)| *id)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ActivityDetails> {
+        self.current()
+            .map(|(_, activity)| activity)
+            .find(|activity| activity.name == name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut ActivityDetails> {
+        self.activities
+            .values_mut()
+            .find(|activity| !activity.is_removed() && activity.name == name)
     }
 
     pub fn add_activity(
         &mut self,
+        storage: &mut dyn Storage,
         activity_name: String,
-        activity: ActivityDetails,
-    ) -> Result<(), &'static str> {
+        mut activity: ActivityDetails,
+    ) -> Result<u64, &'static str> {
+        let activity_name = activity_name.to_uppercase();
         match self.is_in_activities(activity_name.clone()) {
-            false => match activity.frequency {
-                FrequencyType::Invalid => Err("Wrong Frequency Type"),
-                FrequencyType::AllWeek | FrequencyType::WorkingDays | FrequencyType::WeekEnds => {
-                    self.activities
-                        .insert(activity_name.to_uppercase(), activity);
-                    self.save_to_file().map_err(|_| "Failed to save activities")
-                }
-            },
+            false => {
+                activity.check()?;
+                activity.name = activity_name;
+                let id = storage
+                    .insert_activity(&activity)
+                    .map_err(|_| "Failed to save activities")?;
+                self.activities.insert(id, activity);
+                self.last_id = self.last_id.max(id);
+                Ok(id)
+            }
             true => Err("Activity Already Exists"),
         }
     }
 
-    pub fn remove_activity(&mut self, activity_name: String) -> Result<(), &'static str> {
-        self.activities
-            .retain(|activity, _| activity != &activity_name);
-        self.save_to_file().map_err(|_| "Failed to save activities")
+    /// Changes the name and frequency of an activity in place, keeping its history.
+    pub fn edit_activity(
+        &mut self,
+        storage: &mut dyn Storage,
+        activity_name: String,
+        new_name: String,
+        frequency: FrequencyType,
+    ) -> Result<(), &'static str> {
+        let new_name = new_name.trim().to_uppercase();
+        let id = self.id_of(&activity_name).ok_or("Activity Not Found")?;
+        let mut edited = self.activities[&id].clone();
+        edited.frequency = frequency;
+        match self.id_of(&new_name) {
+            _ if new_name.is_empty() => Err("Name Can't Be Empty"),
+            Some(other) if other != id => Err("Activity Already Exists"),
+            _ => {
+                edited.check()?;
+                storage
+                    .edit_activity(id, &new_name, &edited.frequency)
+                    .map_err(|_| "Failed to save activities")?;
+                edited.name = new_name;
+                self.activities.insert(id, edited);
+                Ok(())
+            }
+        }
+    }
+
+    /// Removes the activity from today on, keeping it for the days before.
+    pub fn remove_activity(
+        &mut self,
+        storage: &mut dyn Storage,
+        activity_name: String,
+    ) -> Result<(), &'static str> {
+        let Some(id) = self.id_of(&activity_name) else {
+            return Ok(());
+        };
+        let today = time_utils::current_date();
+        storage
+            .remove_activity(id, today)
+            .map_err(|_| "Failed to save activities")?;
+        if let Some(activity) = self.activities.get_mut(&id) {
+            activity.removed = Some(today);
+        }
+        Ok(())
     }
 
-    pub fn set_activity_done(&mut self, activity_name: String) -> Result<(), &'static str> {
-        match self.is_activity_completed_on_day(activity_name.clone(), 0) {
+    /// Sets the activity done for today, or logs a slip for activities to avoid. Returns the
+    /// timestamp of the completion.
+    pub fn set_activity_done(
+        &mut self,
+        storage: &mut dyn Storage,
+        activity_name: String,
+    ) -> Result<i64, &'static str> {
+        let id = self.id_of(&activity_name).ok_or("Activity Not Found")?;
+        let avoid = self.activities[&id].kind == ActivityKind::Avoid;
+        match self.has_completion_on_day(&activity_name, 0) {
+            true if avoid => Err("Slip Already Logged!"),
             false => {
-                self.activities
-                    .entry(activity_name)
-                    .and_modify(|activity_details| {
-                        activity_details
-                            .completion_timestamps
-                            .insert(time_utils::current_time_timestamp());
-                    });
-                self.save_to_file().map_err(|_| "Failed to save activities")
+                let timestamp = time_utils::current_time_timestamp();
+                let day = LocalDay::of(timestamp);
+                storage
+                    .add_completion(id, timestamp, day)
+                    .map_err(|_| "Failed to save activities")?;
+                self.activities.entry(id).and_modify(|activity_details| {
+                    activity_details.add_completion(timestamp, day);
+                });
+                Ok(timestamp)
             }
             _ => Err("Already Done!"),
         }
     }
 
+    /// Sets the activity done on an earlier `date`, or logs a slip on it. Returns the
+    /// timestamp of the completion, noon of that day.
+    pub fn set_activity_done_on(
+        &mut self,
+        storage: &mut dyn Storage,
+        activity_name: String,
+        date: NaiveDate,
+    ) -> Result<i64, &'static str> {
+        let id = self.id_of(&activity_name).ok_or("Activity Not Found")?;
+        let activity = &self.activities[&id];
+        match (activity.is_done_on(date), activity.kind) {
+            (true, ActivityKind::Avoid) => Err("Slip Already Logged!"),
+            (true, ActivityKind::Do) => Err("Already Done!"),
+            (false, _) => {
+                let timestamp = time_utils::noon_timestamp(date);
+                let day = LocalDay {
+                    date,
+                    ..LocalDay::of(timestamp)
+                };
+                storage
+                    .add_completion(id, timestamp, day)
+                    .map_err(|_| "Failed to save activities")?;
+                if let Some(activity) = self.activities.get_mut(&id) {
+                    activity.add_completion(timestamp, day);
+                }
+                Ok(timestamp)
+            }
+        }
+    }
+
+    /// Takes back the completions, or the slips, of the activity on `date`. Returns them
+    /// with their days.
+    pub fn clear_done_on(
+        &mut self,
+        storage: &mut dyn Storage,
+        activity_name: String,
+        date: NaiveDate,
+    ) -> Result<Vec<(i64, LocalDay)>, &'static str> {
+        let id = self.id_of(&activity_name).ok_or("Activity Not Found")?;
+        let activity = &self.activities[&id];
+        let mut completions: Vec<(i64, LocalDay)> = activity
+            .completion_timestamps
+            .iter()
+            .map(|timestamp| (*timestamp, activity.completion_day(*timestamp)))
+            .filter(|(_, day)| day.date == date)
+            .collect();
+        if completions.is_empty() {
+            return Err("Not Done");
+        }
+        completions.sort_by_key(|(timestamp, _)| *timestamp);
+        for (timestamp, _) in &completions {
+            storage
+                .remove_completion(id, *timestamp)
+                .map_err(|_| "Failed to save activities")?;
+            if let Some(activity) = self.activities.get_mut(&id) {
+                activity.remove_completion(*timestamp);
+            }
+        }
+        Ok(completions)
+    }
+
+    /// Skips the activity for today with `reason`, or changes the reason of a skip. Returns
+    /// the day skipped with the reason it replaced, if any.
+    pub fn skip_activity(
+        &mut self,
+        storage: &mut dyn Storage,
+        activity_name: String,
+        reason: String,
+    ) -> Result<(NaiveDate, Option<String>), &'static str> {
+        let reason = reason.trim().to_string();
+        let id = self.id_of(&activity_name).ok_or("Activity Not Found")?;
+        if reason.is_empty() {
+            return Err("Reason Can't Be Empty");
+        }
+        if self.is_activity_completed_on_day(activity_name, 0)
+            && self.activities[&id].kind == ActivityKind::Do
+        {
+            return Err("Already Done!");
+        }
+        let today = time_utils::current_date();
+        storage
+            .add_skip(id, today, &reason)
+            .map_err(|_| "Failed to save activities")?;
+        let replaced = self
+            .activities
+            .get_mut(&id)
+            .and_then(|activity| activity.skips.insert(today, reason));
+        Ok((today, replaced))
+    }
+
+    /// Adds `amount` to today's progress of a quantitative activity, setting it done once the
+    /// target is reached. Returns true if it is done.
+    pub fn log_progress(
+        &mut self,
+        storage: &mut dyn Storage,
+        activity_name: String,
+        amount: f64,
+    ) -> Result<bool, &'static str> {
+        let timestamp = time_utils::current_time_timestamp();
+        self.log_progress_at(storage, activity_name, amount, timestamp)
+    }
+
+    /// Adds `amount` to today's progress like [`AllActivities::log_progress`], logged at
+    /// `timestamp`.
+    pub fn log_progress_at(
+        &mut self,
+        storage: &mut dyn Storage,
+        activity_name: String,
+        amount: f64,
+        timestamp: i64,
+    ) -> Result<bool, &'static str> {
+        let id = self.id_of(&activity_name).ok_or("Activity Not Found")?;
+        let activity = self.activities.get_mut(&id).ok_or("Activity Not Found")?;
+        if activity.target.is_none() {
+            return Err("Activity Has No Target");
+        }
+        if !(amount.is_finite() && amount > 0.0) {
+            return Err("Invalid Amount");
+        }
+        let day = LocalDay::of(timestamp);
+        storage
+            .add_progress(id, timestamp, amount, day)
+            .map_err(|_| "Failed to save activities")?;
+        activity.add_progress(timestamp, amount, day);
+        if activity.is_target_reached_on(day.date) && !activity.is_done_on(day.date) {
+            storage
+                .add_completion(id, timestamp, day)
+                .map_err(|_| "Failed to save activities")?;
+            activity.add_completion(timestamp, day);
+        }
+        Ok(activity.is_done_on(day.date))
+    }
+
     pub fn is_in_activities(&self, name: String) -> bool {
-        self.activities.contains_key(&name)
+        self.id_of(&name).is_some()
     }
 
     /*
     maybe for later
-    fn purge_all(&mut self) {
+    fn purge_all(&mut self, storage: &mut dyn Storage) {
         self.activities = HashMap::new();
-        self.save_to_file().expect("Failed to save activities")
+        storage.save(self).expect("Failed to save activities")
     }
     */
 
+    /// True if the activity was done on the day, or had no slip for activities to avoid.
     fn is_activity_completed_on_day(&self, activity_name: String, adjustment: i64) -> bool {
-        let activity = self.activities.get(&activity_name);
-        match activity {
-            Some(activity) => activity
-                .completion_timestamps
-                .iter()
-                .any(|timestamp| time_utils::is_timestamp_on_day(*timestamp, adjustment)),
-            None => {
-                println!("Activity not found.");
-                false
-            }
-        }
+        self.get(&activity_name)
+            .is_some_and(|activity| activity.is_completed_on(time_utils::adjusted_date(adjustment)))
+    }
+
+    fn has_completion_on_day(&self, activity_name: &str, adjustment: i64) -> bool {
+        self.get(activity_name)
+            .is_some_and(|activity| activity.is_done_on(time_utils::adjusted_date(adjustment)))
     }
 }
@@ -1,4 +1,8 @@
-use crate::activities_manager::{ActivityDetails, AllActivities, Day, FrequencyType};
+use crate::activities_manager::{
+    self, ActivityDetails, ActivityKind, AllActivities, Day, FrequencyType, Pause, Target,
+};
+use crate::history::{History, Operation};
+use crate::storage::{MemoryStorage, Storage};
 use crate::time_utils;
 use chrono::NaiveDate;
 /// Application result type.
@@ -9,6 +13,17 @@ pub enum InputMode {
     Inactive,
     ActiveName,
     ActiveFrequency,
+    ActiveTarget,
+    /// Amount to add to the progress of [`App::picked_activity`].
+    ActiveProgress,
+    /// New name of [`App::picked_activity`], starting from the current one.
+    ActiveEditName,
+    /// New frequency of [`App::picked_activity`], starting from the current one.
+    ActiveEditFrequency,
+    /// Days to pause [`App::picked_activity`], or the whole tracker if it is empty.
+    ActivePause,
+    /// Why [`App::picked_activity`] is skipped today.
+    ActiveSkip,
 }
 
 /// Application.
@@ -22,15 +37,26 @@ pub struct App {
     pub selected: usize,
     pub todays_activities: Vec<String>,
     pub activities_status: Vec<String>,
+    /// Activities paused today, listed apart from today's ones.
+    pub paused_activities: Vec<String>,
     pub all_activities: AllActivities,
+    pub storage: Box<dyn Storage>,
+    /// Changes that can be undone with `u` and redone with `Ctrl-r`.
+    pub history: History,
     pub new_activity_name: String,
     pub new_activity_frequency: FrequencyType,
+    pub new_activity_target: Option<Target>,
+    pub new_activity_kind: ActivityKind,
+    /// Activity the progress or edit prompts apply to.
+    pub picked_activity: String,
     pub total_perfect_days: i64,
     pub logs: String,
     pub day_status: bool,
     pub activities_till_perfect_day: u8,
     pub status_day: String,
     pub current_day: NaiveDate,
+    /// Days from today to the day listed, negative for past days.
+    pub day_offset: i64,
 }
 
 impl Default for App {
@@ -43,42 +69,91 @@ impl Default for App {
             selected: 0,
             todays_activities: vec![String::from("...")],
             activities_status: vec![String::from("...")],
+            paused_activities: Vec::new(),
             all_activities: AllActivities::new(),
+            storage: Box::new(MemoryStorage::new()),
+            history: History::default(),
             new_activity_name: String::new(),
             new_activity_frequency: FrequencyType::Invalid,
+            new_activity_target: None,
+            new_activity_kind: ActivityKind::Do,
+            picked_activity: String::new(),
             total_perfect_days: 0,
             logs: String::new(),
             day_status: false,
             activities_till_perfect_day: 0,
             status_day: String::new(),
             current_day: time_utils::current_date(),
+            day_offset: 0,
         }
     }
 }
 
 impl App {
-    /// Constructs a new instance of [`App`].
-    pub fn new() -> Self {
-        let mut app = Self::default();
-        app.all_activities =
-            AllActivities::load_from_file().unwrap_or_else(|_| AllActivities::new());
-        let today = Day::today();
-        (app.todays_activities, app.activities_status) = today.today_activities_list();
-        app.is_perfect_day_today();
-        app.activities_till_perfect_day();
-        app.total_perfect_days(-30);
+    /// Constructs a new instance of [`App`] with the activities kept in `storage`.
+    pub fn new(storage: Box<dyn Storage>) -> Self {
+        let (all_activities, recovered_from) = match storage.load() {
+            Ok(loaded) => (loaded.value, loaded.recovered_from),
+            Err(_) => (AllActivities::new(), None),
+        };
+        let mut app = Self {
+            all_activities,
+            storage,
+            ..Self::default()
+        };
+        app.refresh_list();
+        app.refresh_global_status();
         app.day_of_week_status();
-        app.logs = format! {"Today is {}",app.current_day.to_string()};
+        app.logs = match recovered_from {
+            Some(backup) => format!(
+                "Today is {}. Activities file was unreadable, recovered from {}",
+                app.current_day,
+                backup.display()
+            ),
+            None => format!("Today is {}", app.current_day),
+        };
         app
     }
 
+    /// Keeps the undo history in `history`, see [`History::load`].
+    pub fn with_history(mut self, history: History) -> Self {
+        self.history = history;
+        self
+    }
+
     /// Handles the tick event of the terminal.
     /// probably there is more effective way to auto refresh on date change
     pub fn tick(&mut self) {
         match time_utils::is_same_date(self.current_day) {
             true => {}
-            false => *self = Self::new(),
+            false => {
+                let storage = std::mem::replace(&mut self.storage, Box::new(MemoryStorage::new()));
+                let history = std::mem::take(&mut self.history);
+                *self = Self::new(storage).with_history(history)
+            }
+        }
+    }
+
+    /// Reloads the activities after the data was changed outside of this instance.
+    pub fn reload(&mut self) {
+        let all_activities = match self.storage.load() {
+            Ok(loaded) => loaded.value,
+            Err(e) => {
+                self.logs = format!("Error: could not reload activities: {}", e);
+                return;
+            }
+        };
+        // our own saves are reported by the watcher too
+        if all_activities == self.all_activities {
+            return;
         }
+        self.all_activities = all_activities;
+        self.refresh_list();
+        self.selected = self
+            .selected
+            .min(self.todays_activities.len().saturating_sub(1));
+        self.refresh_global_status();
+        self.logs = String::from("Activities were changed elsewhere and reloaded");
     }
 
     /// Set running to false to quit the application.
@@ -120,8 +195,7 @@ impl App {
             let target_char = self
                 .input
                 .char_indices()
-                .filter(|&(idx, _)| idx <= from_left_to_current_index)
-                .last();
+                .rfind(|&(idx, _)| idx <= from_left_to_current_index);
 
             match target_char {
                 Some((idx, ch)) => {
@@ -134,13 +208,14 @@ impl App {
     }
 
     pub fn refresh_global_status(&mut self) {
+        self.paused_activities = self.day(0).paused_activities().to_vec();
         self.is_perfect_day_today();
         self.activities_till_perfect_day();
         self.total_perfect_days(-30);
     }
 
     pub fn is_perfect_day_today(&mut self) {
-        let today = Day::today();
+        let today = self.day(0);
         match today.is_perfect_day() {
             true => self.day_status = true,
             false => self.day_status = false,
@@ -150,19 +225,30 @@ impl App {
     pub fn activities_till_perfect_day(&mut self) {
         match self.day_status {
             true => self.activities_till_perfect_day = 0,
-            false => {
-                self.activities_till_perfect_day = self
-                    .activities_status
-                    .iter()
-                    .filter(|status| **status == String::from("[-]"))
-                    .count() as u8
-            }
+            false => self.activities_till_perfect_day = self.day(0).remaining() as u8,
         };
     }
 
+    /// Counts the perfect days among the last `-period` days and today, looking one day
+    /// further back for each paused day.
     pub fn total_perfect_days(&mut self, period: i64) {
-        self.total_perfect_days = (period..=0)
-            .filter(|&i| Day::get_day(i).is_perfect_day())
+        // no day before the first activity started can be perfect
+        let first_start = self
+            .all_activities
+            .activities
+            .values()
+            .map(|activity| time_utils::local_date(activity.start))
+            .min()
+            .unwrap_or(self.current_day);
+        let oldest = (first_start - time_utils::current_date())
+            .num_days()
+            .min(period);
+        self.total_perfect_days = (oldest..=0)
+            .rev()
+            .map(|i| self.day(i))
+            .filter(|day| !day.is_excused())
+            .take((1 - period) as usize)
+            .filter(Day::is_perfect_day)
             .count() as i64;
     }
 
@@ -171,27 +257,46 @@ impl App {
     }
 
     pub fn convert_activity_frequency(&mut self) {
-        self.new_activity_frequency = match self.input.as_str() {
-            "AW" => FrequencyType::AllWeek,
-            "WD" => FrequencyType::WorkingDays,
-            "WE" => FrequencyType::WeekEnds,
-            _ => {
-                self.logs = String::from("Invalid frequency. Please enter AW, WD, or WE.");
-                FrequencyType::Invalid
-            }
-        };
+        self.new_activity_frequency = FrequencyType::from_code(&self.input);
+        if self.new_activity_frequency == FrequencyType::Invalid {
+            self.logs = String::from(
+                "Invalid frequency. Please enter AW, WD, WE, days like MON,WED,FRI, a quota like 3/W, an interval like 3D, a monthly rule like 1ST or LAST SUN or a rule like RRULE:FREQ=MONTHLY;BYMONTHDAY=-1.",
+            );
+        }
+    }
+
+    /// Reads the target of the new activity, none for an empty input.
+    pub fn convert_activity_target(&mut self) -> bool {
+        self.new_activity_target = None;
+        if self.input.trim().is_empty() {
+            return true;
+        }
+        self.new_activity_target = Target::from_code(&self.input);
+        if self.new_activity_target.is_none() {
+            self.logs = String::from(
+                "Invalid target. Please enter an amount and a unit like 2 km, 8 glasses or 30 min, or nothing.",
+            );
+        }
+        self.new_activity_target.is_some()
     }
 
     pub fn add_new_activity(&mut self) {
-        let activity_details = ActivityDetails::new_activity(self.new_activity_frequency.clone());
-        match self
-            .all_activities
-            .add_activity(self.new_activity_name.clone(), activity_details)
-        {
-            Ok(_) => {
+        let activity_details = ActivityDetails::new_activity(self.new_activity_frequency.clone())
+            .with_target(self.new_activity_target.clone())
+            .with_kind(self.new_activity_kind);
+        match self.all_activities.add_activity(
+            self.storage.as_mut(),
+            self.new_activity_name.clone(),
+            activity_details,
+        ) {
+            Ok(id) => {
+                self.history.record(Operation::Add {
+                    id,
+                    activity: self.all_activities.activities[&id].clone(),
+                });
                 self.clear_new_activity_data();
                 self.refresh_global_status();
-                self.logs = format!("Activity is added!");
+                self.logs = String::from("Activity is added!");
             }
             Err(e) => {
                 self.logs = format!("Error: {}", e);
@@ -202,22 +307,45 @@ impl App {
     fn clear_new_activity_data(&mut self) {
         self.new_activity_name.clear();
         self.new_activity_frequency = FrequencyType::Invalid;
-        let today = Day::today();
-        (self.todays_activities, self.activities_status) = today.today_activities_list();
+        self.new_activity_target = None;
+        self.new_activity_kind = ActivityKind::Do;
+        self.refresh_list();
     }
 
+    /// Sets the activity done, or asks for the amount done of quantitative activities. On
+    /// past days it toggles the completion instead.
     pub fn set_activity_done(&mut self, activity_index: usize) {
+        if self.day_offset != 0 {
+            self.toggle_done(activity_index);
+            return;
+        }
         if let Some(activity) = self.todays_activities.get(activity_index) {
+            let details = self.all_activities.get(activity);
+            let avoid = details.is_some_and(|details| details.kind == ActivityKind::Avoid);
+            if let Some(target) = details.and_then(|details| details.target.as_ref()) {
+                self.picked_activity = activity.clone();
+                self.input_mode = InputMode::ActiveProgress;
+                self.logs = match target.unit.is_empty() {
+                    true => format!("How much of {} was done?", activity),
+                    false => format!("How many {} of {} were done?", target.unit, activity),
+                };
+                return;
+            }
             match self
                 .all_activities
-                .set_activity_done(String::from(activity.clone()))
+                .set_activity_done(self.storage.as_mut(), activity.clone())
             {
-                Ok(_) => {
-                    self.logs = format!("Activity is set done!");
+                Ok(timestamp) => {
+                    if let Some(id) = self.all_activities.id_of(activity) {
+                        let operation = Operation::done(&self.all_activities, id, timestamp);
+                        self.history.record(operation);
+                    }
+                    self.logs = match avoid {
+                        true => String::from("Slip is logged!"),
+                        false => String::from("Activity is set done!"),
+                    };
                     // Update today's activities list
-                    let today = Day::today();
-                    (self.todays_activities, self.activities_status) =
-                        today.today_activities_list();
+                    self.refresh_list();
                     self.refresh_global_status();
                 }
                 Err(e) => {
@@ -227,18 +355,293 @@ impl App {
         }
     }
 
+    /// Sets the activity done on the past day listed, or takes back its completion.
+    fn toggle_done(&mut self, activity_index: usize) {
+        let Some(activity) = self.todays_activities.get(activity_index).cloned() else {
+            return;
+        };
+        let date = self.listed_date();
+        // removed activities are still listed on the days before their removal, maybe with
+        // a current one taking their name afterwards
+        let current = self.all_activities.id_of(&activity);
+        let Some(id) = current.filter(|id| self.all_activities.activities[id].exists_on(date))
+        else {
+            self.logs = String::from("Removed activities can't be changed");
+            return;
+        };
+        let storage = self.storage.as_mut();
+        let result = match self.all_activities.activities[&id].is_done_on(date) {
+            true => self
+                .all_activities
+                .clear_done_on(storage, activity, date)
+                .map(|completions| Operation::Cleared { id, completions }),
+            false => self
+                .all_activities
+                .set_activity_done_on(storage, activity, date)
+                .map(|timestamp| Operation::done(&self.all_activities, id, timestamp)),
+        };
+        match result {
+            Ok(operation) => {
+                self.logs = format!("{} on {}", operation.describe(&self.all_activities), date);
+                self.history.record(operation);
+                self.refresh_activities();
+            }
+            Err(e) => {
+                self.logs = format!("Error: {}", e);
+            }
+        }
+    }
+
+    /// Date of the day listed.
+    pub fn listed_date(&self) -> NaiveDate {
+        time_utils::adjusted_date(self.day_offset)
+    }
+
+    /// Lists the day before the one listed.
+    pub fn previous_day(&mut self) {
+        self.list_day(self.day_offset - 1);
+    }
+
+    /// Lists the day after the one listed, up to today.
+    pub fn next_day(&mut self) {
+        match self.day_offset < 0 {
+            true => self.list_day(self.day_offset + 1),
+            false => self.logs = String::from("Days after today can't be logged yet"),
+        }
+    }
+
+    fn list_day(&mut self, day_offset: i64) {
+        self.day_offset = day_offset;
+        self.refresh_activities();
+        self.logs = match day_offset {
+            0 => format!("Today is {}", self.current_day),
+            _ => format!(
+                "Showing {}, press `d` to set an activity done or not on that day",
+                self.listed_date()
+            ),
+        };
+    }
+
+    /// Adds the amount in the input to the progress of the activity picked with `d`.
+    pub fn log_progress(&mut self) {
+        let Some(amount) = activities_manager::parse_amount(&self.input) else {
+            self.logs = String::from("Invalid amount. Please enter a number like 1.2");
+            return;
+        };
+        let timestamp = time_utils::current_time_timestamp();
+        let was_done = self
+            .all_activities
+            .get(&self.picked_activity)
+            .is_some_and(|activity| activity.is_done_on(time_utils::current_date()));
+        match self.all_activities.log_progress_at(
+            self.storage.as_mut(),
+            self.picked_activity.clone(),
+            amount,
+            timestamp,
+        ) {
+            Ok(done) => {
+                if let Some(id) = self.all_activities.id_of(&self.picked_activity) {
+                    let activity = &self.all_activities.activities[&id];
+                    let completion =
+                        (done && !was_done).then(|| activity.completion_day(timestamp));
+                    self.history.record(Operation::Progress {
+                        id,
+                        timestamp,
+                        amount,
+                        day: activity.progress_day(timestamp),
+                        completion,
+                    });
+                }
+                self.logs = match done {
+                    true => String::from("Target reached, activity is set done!"),
+                    false => String::from("Progress is logged!"),
+                };
+                self.refresh_list();
+                self.refresh_global_status();
+            }
+            Err(e) => {
+                self.logs = format!("Error: {}", e);
+            }
+        }
+    }
+
+    /// Starts editing the activity, asking for its name with the current one filled in.
+    pub fn start_edit(&mut self, activity_index: usize) {
+        if self.day_offset != 0 {
+            self.logs = String::from("Only today can be edited, press `]` to go back to it");
+            return;
+        }
+        if let Some(activity) = self.todays_activities.get(activity_index) {
+            self.picked_activity = activity.clone();
+            self.input = activity.clone();
+            self.cursor_position = self.input.len();
+            self.input_mode = InputMode::ActiveEditName;
+            self.logs = format!("Editing {}", activity);
+        }
+    }
+
+    /// Keeps the name in the input and asks for the frequency, filled in the same way.
+    pub fn edit_frequency(&mut self) {
+        self.add_new_activity_name();
+        self.input = self
+            .all_activities
+            .get(&self.picked_activity)
+            .map(|activity| activity.frequency.code())
+            .unwrap_or_default();
+        self.cursor_position = self.input.len();
+        self.input_mode = InputMode::ActiveEditFrequency;
+    }
+
+    /// Saves the name and the frequency in the input for the activity picked with `e`.
+    pub fn edit_activity(&mut self) {
+        self.convert_activity_frequency();
+        if !self.new_activity_frequency.is_valid() {
+            return;
+        }
+        let id = self.all_activities.id_of(&self.picked_activity);
+        let before = self
+            .all_activities
+            .get(&self.picked_activity)
+            .map(|activity| (activity.name.clone(), activity.frequency.clone()));
+        match self.all_activities.edit_activity(
+            self.storage.as_mut(),
+            self.picked_activity.clone(),
+            self.new_activity_name.clone(),
+            self.new_activity_frequency.clone(),
+        ) {
+            Ok(_) => {
+                if let (Some(id), Some(before)) = (id, before) {
+                    let activity = &self.all_activities.activities[&id];
+                    let after = (activity.name.clone(), activity.frequency.clone());
+                    self.history.record(Operation::Edit { id, before, after });
+                }
+                self.clear_new_activity_data();
+                self.refresh_global_status();
+                self.logs = String::from("Activity is edited!");
+            }
+            Err(e) => {
+                self.logs = format!("Error: {}", e);
+            }
+        }
+    }
+
+    /// Asks why the activity is skipped today.
+    pub fn start_skip(&mut self, activity_index: usize) {
+        if self.day_offset != 0 {
+            self.logs = String::from("Only today can be skipped, press `]` to go back to it");
+            return;
+        }
+        if let Some(activity) = self.todays_activities.get(activity_index) {
+            self.picked_activity = activity.clone();
+            self.input_mode = InputMode::ActiveSkip;
+        }
+    }
+
+    /// Skips the activity picked with `s` for the reason in the input.
+    pub fn skip_activity(&mut self) {
+        match self.all_activities.skip_activity(
+            self.storage.as_mut(),
+            self.picked_activity.clone(),
+            self.input.clone(),
+        ) {
+            Ok((date, replaced)) => {
+                if let Some(id) = self.all_activities.id_of(&self.picked_activity) {
+                    self.history.record(Operation::Skip {
+                        id,
+                        date,
+                        reason: self.input.trim().to_string(),
+                        replaced,
+                    });
+                }
+                self.logs = String::from("Activity is skipped!");
+                self.refresh_list();
+                self.refresh_global_status();
+            }
+            Err(e) => {
+                self.logs = format!("Error: {}", e);
+            }
+        }
+    }
+
+    /// Asks for the days to pause the activity, or the whole tracker without one.
+    pub fn start_pause(&mut self, activity_index: Option<usize>) {
+        if activity_index.is_some() && self.day_offset != 0 {
+            self.logs = String::from("Only today can be paused, press `]` to go back to it");
+            return;
+        }
+        self.picked_activity = match activity_index {
+            Some(index) => match self.todays_activities.get(index) {
+                Some(activity) => activity.clone(),
+                None => return,
+            },
+            None => String::new(),
+        };
+        self.input_mode = InputMode::ActivePause;
+    }
+
+    /// Pauses the activity picked with `p`, or the whole tracker with `v`, for the days in
+    /// the input. An empty input for the tracker resumes everything instead.
+    pub fn pause(&mut self) {
+        let whole_tracker = self.picked_activity.is_empty();
+        let result = match Pause::from_code(&self.input, time_utils::current_date()) {
+            None if whole_tracker && self.input.trim().is_empty() => self
+                .all_activities
+                .resume_all(self.storage.as_mut())
+                .map(|_| "Everything is resumed!"),
+            None => {
+                self.logs = String::from(
+                    "Invalid dates. Please enter the last day like 2024-01-27, or the first and last ones like 2024-01-20..2024-01-27.",
+                );
+                return;
+            }
+            Some(pause) => self
+                .all_activities
+                .pause(
+                    self.storage.as_mut(),
+                    (!whole_tracker).then(|| self.picked_activity.clone()),
+                    pause,
+                )
+                .map(|_| match whole_tracker {
+                    true => "Tracker is paused!",
+                    false => "Activity is paused!",
+                }),
+        };
+        match result {
+            Ok(message) => {
+                self.logs = String::from(message);
+                self.refresh_list();
+                self.selected = self
+                    .selected
+                    .min(self.todays_activities.len().saturating_sub(1));
+                self.refresh_global_status();
+            }
+            Err(e) => {
+                self.logs = format!("Error: {}", e);
+            }
+        }
+    }
+
     pub fn remove_activity(&mut self, activity_index: usize) {
+        if self.day_offset != 0 {
+            self.logs = String::from("Only today can be removed, press `]` to go back to it");
+            return;
+        }
         if let Some(activity) = self.todays_activities.get(activity_index) {
+            let removed = self
+                .all_activities
+                .id_of(activity)
+                .map(|id| (id, self.all_activities.activities[&id].clone()));
             match self
                 .all_activities
-                .remove_activity(String::from(activity.clone()))
+                .remove_activity(self.storage.as_mut(), activity.clone())
             {
                 Ok(_) => {
-                    self.logs = format!("Activity is removed!");
+                    if let Some((id, activity)) = removed {
+                        self.history.record(Operation::Remove { id, activity });
+                    }
+                    self.logs = String::from("Activity is removed!");
                     // Update today's activities list
-                    let today = Day::today();
-                    (self.todays_activities, self.activities_status) =
-                        today.today_activities_list();
+                    self.refresh_list();
                     self.refresh_global_status();
                 }
 
@@ -248,11 +651,58 @@ impl App {
             }
         }
     }
+
+    /// Reverts the latest change made with `d`, `n`, `a`, `e` or `r`.
+    pub fn undo(&mut self) {
+        let result = self
+            .history
+            .undo(&mut self.all_activities, self.storage.as_mut());
+        self.logs = match result {
+            Ok(operation) => format!("Undone: {}", operation.describe(&self.all_activities)),
+            Err(e) => format!("Error: {}", e),
+        };
+        self.refresh_activities();
+    }
+
+    /// Applies the latest undone change again.
+    pub fn redo(&mut self) {
+        let result = self
+            .history
+            .redo(&mut self.all_activities, self.storage.as_mut());
+        self.logs = match result {
+            Ok(operation) => format!("Redone: {}", operation.describe(&self.all_activities)),
+            Err(e) => format!("Error: {}", e),
+        };
+        self.refresh_activities();
+    }
+
+    /// Day `adjustment` days from today, with its completions looked up by the storage when
+    /// it keeps them indexed by day.
+    fn day(&self, adjustment: i64) -> Day {
+        let date = time_utils::adjusted_date(adjustment);
+        let completed = self.storage.completed_on(date).ok().flatten();
+        Day::get_day_from(&self.all_activities, adjustment, completed.as_ref())
+    }
+
+    /// Lists the activities of the day listed with their status.
+    pub fn refresh_list(&mut self) {
+        let day = self.day(self.day_offset);
+        (self.todays_activities, self.activities_status) = day.today_activities_list();
+    }
+
+    fn refresh_activities(&mut self) {
+        self.refresh_list();
+        self.selected = self
+            .selected
+            .min(self.todays_activities.len().saturating_sub(1));
+        self.refresh_global_status();
+    }
+
     /*
     maybe for later
     pub fn purge_all_activities(&mut self) {
-        self.all_activities.purge_all();
-        self.day = Day::today(); // Update day activities after purging all activities
+        self.all_activities.purge_all(self.storage.as_mut());
+        self.day = Day::today(&self.all_activities); // Update day activities after purging all activities
     }
     */
 }
@@ -0,0 +1,151 @@
+use crate::interchange::{Format, ImportMode};
+use crate::storage::StorageKind;
+use crate::time_utils;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "Usage: yacht [OPTIONS] [COMMAND]
+
+Commands:
+  export              print every activity and its history
+      --format <FORMAT>     json (default), toml, csv for one row per
+                            activity per scheduled day, or ics for a
+                            calendar with a recurring event per activity
+      --output <PATH>       write to PATH instead of stdout
+  import <PATH>       import activities exported with `export`
+      --format <FORMAT>     json, toml or ics for the recurring events of a
+                            calendar, guessed from the extension by default
+      --mode <MODE>         merge (default) adds to the current activities,
+                            replace makes them exactly the imported ones
+      --dry-run             only list what would change
+  import-loop <PATH>  import a Loop Habit Tracker CSV export, zipped or extracted
+      --mode, --dry-run     as for import
+  import-bin [PATH]   import a bincode activities file into an empty SQLite database
+                      (defaults to the activities.bin in the data directory)
+
+Options:
+  --data-file <PATH>  use PATH as the data file
+  --storage <KIND>    storage backend: file (default) or sqlite
+  --day-start <HOUR>  hour from 0 (default) to 12 that days start at, so
+                      that late nights count for the day before
+  -h, --help          print this help
+
+Environment:
+  YACHT_DATA_FILE     data file used when --data-file is not given
+  YACHT_STORAGE       storage backend used when --storage is not given
+  YACHT_DAY_START     hour days start at when --day-start is not given
+";
+
+/// What to do instead of running the TUI.
+#[derive(Debug, Default, PartialEq)]
+pub enum Command {
+    /// Run the terminal user interface.
+    #[default]
+    Tui,
+    /// Import a bincode file into the SQLite database.
+    ImportBin(Option<PathBuf>),
+    /// Export the activities as JSON or TOML.
+    Export {
+        format: Format,
+        output: Option<PathBuf>,
+    },
+    /// Import activities from a JSON or TOML export.
+    Import {
+        path: PathBuf,
+        format: Option<Format>,
+        mode: ImportMode,
+        dry_run: bool,
+    },
+    /// Import habits from a Loop Habit Tracker export.
+    ImportLoop {
+        path: PathBuf,
+        mode: ImportMode,
+        dry_run: bool,
+    },
+}
+
+/// Parsed command line arguments.
+#[derive(Debug, Default, PartialEq)]
+pub struct Cli {
+    pub data_file: Option<PathBuf>,
+    pub storage: Option<StorageKind>,
+    pub day_start: Option<u32>,
+    pub command: Command,
+    pub help: bool,
+}
+
+impl Cli {
+    /// Parses the arguments, without the program name.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Cli, String> {
+        let mut cli = Cli::default();
+        let mut positional = Vec::new();
+        // command options, checked against the command once it is known
+        let mut format = None;
+        let mut output = None;
+        let mut mode = None;
+        let mut dry_run = false;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            // both `--flag value` and `--flag=value` are accepted
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .filter(|value| !value.is_empty())
+                    .ok_or_else(|| format!("{} requires a value", flag))
+            };
+            match flag {
+                "-h" | "--help" => cli.help = true,
+                "--data-file" => cli.data_file = Some(PathBuf::from(value()?)),
+                "--storage" => cli.storage = Some(value()?.parse()?),
+                "--day-start" => cli.day_start = Some(time_utils::parse_day_start(&value()?)?),
+                "--format" => format = Some(value()?.parse()?),
+                "--output" => output = Some(PathBuf::from(value()?)),
+                "--mode" => mode = Some(value()?.parse()?),
+                "--dry-run" => dry_run = true,
+                _ if flag.starts_with('-') => return Err(format!("Unknown argument: {}", arg)),
+                _ => positional.push(arg),
+            }
+        }
+
+        let mut positional = positional.into_iter();
+        cli.command = match positional.next().as_deref() {
+            None => Command::Tui,
+            Some("import-bin") => Command::ImportBin(positional.next().map(PathBuf::from)),
+            Some("export") => Command::Export {
+                format: format.take().unwrap_or_default(),
+                output: output.take(),
+            },
+            Some("import") => Command::Import {
+                path: positional
+                    .next()
+                    .map(PathBuf::from)
+                    .ok_or_else(|| String::from("import requires a path"))?,
+                format: format.take(),
+                mode: mode.take().unwrap_or_default(),
+                dry_run: std::mem::take(&mut dry_run),
+            },
+            Some("import-loop") => Command::ImportLoop {
+                path: positional
+                    .next()
+                    .map(PathBuf::from)
+                    .ok_or_else(|| String::from("import-loop requires a path"))?,
+                mode: mode.take().unwrap_or_default(),
+                dry_run: std::mem::take(&mut dry_run),
+            },
+            Some(command) => return Err(format!("Unknown command: {}", command)),
+        };
+        if format.is_some() || output.is_some() || mode.is_some() || dry_run {
+            return Err(String::from(
+                "--format, --output, --mode and --dry-run only apply to export and import",
+            ));
+        }
+        match positional.next() {
+            Some(arg) => Err(format!("Unexpected argument: {}", arg)),
+            None => Ok(cli),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Commands that run instead of the TUI.
+
+use crate::activities_manager::AllActivities;
+use crate::app::AppResult;
+use crate::cli::Command;
+use crate::csv_export;
+use crate::ics_export;
+use crate::ics_import;
+use crate::interchange::{self, Document, Format, ImportMode};
+use crate::loop_import;
+use crate::storage::Storage;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Runs `command` against `storage`. Does nothing for [`Command::Tui`].
+pub fn run(command: Command, storage: &mut dyn Storage) -> AppResult<()> {
+    match command {
+        Command::Tui | Command::ImportBin(_) => {}
+        Command::Export {
+            format: Format::Csv,
+            output,
+        } => {
+            let all = current_activities(storage)?;
+            match output {
+                Some(path) => csv_export::write_csv(&all, fs::File::create(path)?)?,
+                None => csv_export::write_csv(&all, io::stdout().lock())?,
+            }
+        }
+        Command::Export {
+            format: Format::Ics,
+            output,
+        } => {
+            let all = current_activities(storage)?;
+            let id = storage.calendar_id()?;
+            match output {
+                Some(path) => ics_export::write_ics(&all, &id, fs::File::create(path)?)?,
+                None => ics_export::write_ics(&all, &id, io::stdout().lock())?,
+            }
+        }
+        Command::Export { format, output } => {
+            let text = Document::from_activities(&current_activities(storage)?).render(format)?;
+            match output {
+                Some(path) => fs::write(path, text)?,
+                None => print!("{}", text),
+            }
+        }
+        Command::Import {
+            path,
+            format,
+            mode,
+            dry_run,
+        } => match format.or_else(|| Format::from_path(&path)) {
+            Some(Format::Ics) => {
+                let calendar = ics_import::read_calendar(&path)?;
+                for line in &calendar.report {
+                    println!("! {}", line);
+                }
+                import(storage, &calendar.activities, mode, dry_run)?;
+            }
+            format => {
+                let document = interchange::read_document(&path, format)?;
+                import(storage, &document.to_activities()?, mode, dry_run)?;
+            }
+        },
+        Command::ImportLoop {
+            path,
+            mode,
+            dry_run,
+        } => {
+            let export = loop_import::read_export(&path)?;
+            for line in &export.report {
+                println!("! {}", line);
+            }
+            import(storage, &export.activities, mode, dry_run)?;
+        }
+    }
+    Ok(())
+}
+
+fn import(
+    storage: &mut dyn Storage,
+    imported: &AllActivities,
+    mode: ImportMode,
+    dry_run: bool,
+) -> AppResult<()> {
+    let plan = interchange::plan_import(&current_activities(storage)?, imported, mode);
+    for change in &plan.changes {
+        println!("{}", change);
+    }
+    match (plan.changes.is_empty(), dry_run) {
+        (true, _) => println!("Nothing to import"),
+        (false, true) => println!("Dry run, nothing was changed"),
+        (false, false) => {
+            storage.save(&plan.activities)?;
+            println!("Imported {} changes", plan.changes.len());
+        }
+    }
+    Ok(())
+}
+
+// nothing saved yet is the same as no activities for the commands
+fn current_activities(storage: &dyn Storage) -> AppResult<AllActivities> {
+    match storage.exists() {
+        true => Ok(storage.load()?.value),
+        false => Ok(AllActivities::new()),
+    }
+}
+
+/// Imports a bincode file into the SQLite database at `database`.
+#[cfg(feature = "sqlite")]
+pub fn import_bin(database: &Path, source: Option<PathBuf>) -> AppResult<()> {
+    use crate::data_location::{DataLocation, ACTIVITIES_FILE};
+    use crate::sqlite_storage::SqliteStorage;
+
+    let source = source.unwrap_or_else(|| DataLocation::default_for(ACTIVITIES_FILE).path);
+    let count = SqliteStorage::open(database)?.import_from_file(&source)?;
+    println!(
+        "Imported {} activities from {} into {}",
+        count,
+        source.display(),
+        database.display()
+    );
+    Ok(())
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn import_bin(_database: &Path, _source: Option<PathBuf>) -> AppResult<()> {
+    Err("yacht was built without the sqlite feature".into())
+}
@@ -0,0 +1,82 @@
+//! Flat CSV of the completion history, one row per activity per day it was due.
+//!
+//! Days with a completion outside the schedule get a row too, with `scheduled` false, and
+//! paused days are not scheduled.
+
+use crate::activities_manager::{ActivityKind, AllActivities};
+use crate::time_utils;
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::io::{self, Write};
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct CsvRow {
+    pub date: NaiveDate,
+    pub activity: String,
+    pub frequency: String,
+    pub scheduled: bool,
+    /// For activities to avoid, true unless a slip was logged.
+    pub done: bool,
+    /// Local time of the completion when it was logged, or of the slip for activities to
+    /// avoid.
+    pub completion_time: String,
+    /// Why the activity was skipped that day, empty if it wasn't.
+    pub skip_reason: String,
+}
+
+/// Rows from each activity's start until `today`, or until it was removed, sorted by date
+/// and activity.
+pub fn rows(all: &AllActivities, today: NaiveDate) -> Vec<CsvRow> {
+    let mut rows = Vec::new();
+    for activity in all.activities.values() {
+        let name = &activity.name;
+        let start = time_utils::local_date(activity.start).min(today);
+        let days = start.iter_days().take_while(|date| *date <= today);
+        for date in days.take_while(|date| activity.exists_on(*date)) {
+            let paused = all.is_paused_on(date) || activity.is_paused_on(date);
+            let scheduled = activity.is_due_on(date) && !paused;
+            let mut completions: Vec<i64> = activity
+                .completion_timestamps
+                .iter()
+                .copied()
+                .filter(|timestamp| activity.completion_day(*timestamp).date == date)
+                .collect();
+            if !scheduled && completions.is_empty() {
+                continue;
+            }
+            completions.sort();
+            rows.push(CsvRow {
+                date,
+                activity: name.clone(),
+                frequency: activity.frequency.code(),
+                scheduled,
+                done: match activity.kind {
+                    ActivityKind::Do => !completions.is_empty(),
+                    ActivityKind::Avoid => completions.is_empty(),
+                },
+                completion_time: completions
+                    .iter()
+                    .map(|timestamp| {
+                        activity
+                            .completion_day(*timestamp)
+                            .time_of(*timestamp)
+                            .format("%Y-%m-%d %H:%M:%S")
+                            .to_string()
+                    })
+                    .collect::<Vec<_>>()
+                    .join(";"),
+                skip_reason: activity.skips.get(&date).cloned().unwrap_or_default(),
+            });
+        }
+    }
+    rows.sort_by(|a, b| (a.date, &a.activity).cmp(&(b.date, &b.activity)));
+    rows
+}
+
+pub fn write_csv<W: Write>(all: &AllActivities, writer: W) -> io::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    for row in rows(all, time_utils::current_date()) {
+        writer.serialize(row).map_err(io::Error::other)?;
+    }
+    writer.flush()
+}
@@ -0,0 +1,127 @@
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// File name of the bincode activities file.
+pub const ACTIVITIES_FILE: &str = "activities.bin";
+
+/// Environment variable that overrides the data file location.
+pub const DATA_FILE_ENV: &str = "YACHT_DATA_FILE";
+
+/// Directory created under `$XDG_DATA_HOME` for yacht's data.
+const APP_DIR: &str = "yacht";
+
+/// Where the data file location came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataFileSource {
+    /// `--data-file` command line flag.
+    Flag,
+    /// `YACHT_DATA_FILE` environment variable.
+    Env,
+    /// `$XDG_DATA_HOME/yacht/` (or `~/.local/share/yacht/`).
+    Xdg,
+    /// No home directory could be found, falling back to the working directory.
+    WorkingDir,
+}
+
+/// Resolved location of the activities data file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataLocation {
+    pub path: PathBuf,
+    pub source: DataFileSource,
+}
+
+impl DataLocation {
+    /// Resolves the data file from the flag, the environment and XDG directories, in that order.
+    ///
+    /// `file_name` is the name of the data file in the XDG data directory.
+    pub fn resolve(flag: Option<PathBuf>, file_name: &str) -> DataLocation {
+        Self::resolve_from(
+            flag,
+            env::var_os(DATA_FILE_ENV),
+            env::var_os("XDG_DATA_HOME"),
+            env::var_os("HOME"),
+            file_name,
+        )
+    }
+
+    /// Location of `file_name` in the XDG data directory, ignoring any override.
+    pub fn default_for(file_name: &str) -> DataLocation {
+        Self::resolve_from(
+            None,
+            None,
+            env::var_os("XDG_DATA_HOME"),
+            env::var_os("HOME"),
+            file_name,
+        )
+    }
+
+    pub fn resolve_from(
+        flag: Option<PathBuf>,
+        env_file: Option<OsString>,
+        xdg_data_home: Option<OsString>,
+        home: Option<OsString>,
+        file_name: &str,
+    ) -> DataLocation {
+        if let Some(path) = flag {
+            return DataLocation {
+                path,
+                source: DataFileSource::Flag,
+            };
+        }
+        if let Some(path) = env_file.filter(|value| !value.is_empty()) {
+            return DataLocation {
+                path: PathBuf::from(path),
+                source: DataFileSource::Env,
+            };
+        }
+        // relative XDG_DATA_HOME values are invalid per the spec and must be ignored
+        let data_home = xdg_data_home
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| {
+                home.filter(|value| !value.is_empty())
+                    .map(|home| PathBuf::from(home).join(".local").join("share"))
+            });
+        match data_home {
+            Some(data_home) => DataLocation {
+                path: data_home.join(APP_DIR).join(file_name),
+                source: DataFileSource::Xdg,
+            },
+            None => DataLocation {
+                path: PathBuf::from(file_name),
+                source: DataFileSource::WorkingDir,
+            },
+        }
+    }
+
+    /// Moves `./activities.bin` left by older versions to the XDG location.
+    ///
+    /// Only applies to the default location and only when nothing is stored there yet.
+    /// Returns the path of the migrated file, if any.
+    pub fn migrate_legacy_file(&self) -> io::Result<Option<PathBuf>> {
+        self.migrate_from(Path::new(ACTIVITIES_FILE))
+    }
+
+    pub fn migrate_from(&self, legacy: &Path) -> io::Result<Option<PathBuf>> {
+        if self.source != DataFileSource::Xdg || self.path.exists() || !legacy.is_file() {
+            return Ok(None);
+        }
+        ensure_parent_dir(&self.path)?;
+        // rename fails across file systems, copy and remove in that case
+        if fs::rename(legacy, &self.path).is_err() {
+            fs::copy(legacy, &self.path)?;
+            fs::remove_file(legacy)?;
+        }
+        Ok(Some(legacy.to_path_buf()))
+    }
+}
+
+pub fn ensure_parent_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => fs::create_dir_all(parent),
+        _ => Ok(()),
+    }
+}
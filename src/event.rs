@@ -1,5 +1,7 @@
 use crate::app::AppResult;
-use crossterm::event::{self, Event as CrosstermEvent, KeyEvent, KeyEventKind, MouseEvent};
+use crossterm::event::{self, Event as CrosstermEvent, KeyEvent, MouseEvent};
+use std::fs;
+use std::path::PathBuf;
 use std::sync::mpsc;
 use std::thread;
 use std::time::{Duration, Instant};
@@ -15,6 +17,8 @@ pub enum Event {
     Mouse(MouseEvent),
     /// Terminal resize.
     Resize(u16, u16),
+    /// The data file was changed, possibly by another instance.
+    DataChanged,
 }
 
 /// Terminal event handler.
@@ -45,15 +49,16 @@ impl EventHandler {
 
                     if event::poll(timeout).expect("failed to poll new events") {
                         match event::read().expect("unable to read event") {
-                            CrosstermEvent::Key(key_event) =>
-                                {
-                                    // Only handle key events with `KeyEventKind::Press`
-                                    if key_event.kind == crossterm::event::KeyEventKind::Press {
-                                        sender.send(Event::Key(key_event)).expect("failed to send terminal event");
-                                    }
+                            CrosstermEvent::Key(key_event) => {
+                                // Only handle key events with `KeyEventKind::Press`
+                                if key_event.kind == crossterm::event::KeyEventKind::Press {
+                                    sender
+                                        .send(Event::Key(key_event))
+                                        .expect("failed to send terminal event");
+                                }
 
-                                    Ok(())
-                                },
+                                Ok(())
+                            }
                             CrosstermEvent::Mouse(e) => sender.send(Event::Mouse(e)),
                             CrosstermEvent::Resize(w, h) => sender.send(Event::Resize(w, h)),
                             CrosstermEvent::FocusGained => Ok(()),
@@ -77,6 +82,32 @@ impl EventHandler {
         }
     }
 
+    /// Polls `path` every `interval` milliseconds and sends [`Event::DataChanged`] when its
+    /// size or modification time changes, including when it is replaced by an atomic save.
+    pub fn watch(&self, path: PathBuf, interval: u64) {
+        let sender = self.sender.clone();
+        let interval = Duration::from_millis(interval);
+        let fingerprint = move || {
+            fs::metadata(&path)
+                .ok()
+                .map(|metadata| (metadata.len(), metadata.modified().ok()))
+        };
+        thread::spawn(move || {
+            let mut last = fingerprint();
+            loop {
+                thread::sleep(interval);
+                let current = fingerprint();
+                if current != last {
+                    last = current;
+                    // the receiver is gone once the TUI exits
+                    if sender.send(Event::DataChanged).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
     /// Receive the next event from the handler thread.
     ///
     /// This function will always block the current thread if
@@ -0,0 +1,706 @@
+//! On-disk layout of the activities file.
+//!
+//! A file starts with [`MAGIC`], followed by the format version as a little endian `u16`
+//! and the bincode encoded [`AllActivities`]. Files written before the header existed
+//! have no magic and are treated as version 0.
+//!
+//! Every change to the serialized types must bump [`CURRENT_VERSION`] and add a step to
+//! [`decode_payload`], so older files keep loading. Changes the older payloads don't decode
+//! with also freeze the previous layout in a `vN` module to upgrade from. A sample file of
+//! each version lives in `tests/fixtures/`.
+//!
+//! The `vN` modules never use the live types, since bincode stores enum variants by index
+//! and struct fields by order. Each type they store is copied in the module of the version
+//! that introduced it, reused as is by the later ones, and converted to the live type by
+//! the last upgrade.
+
+use crate::activities_manager::AllActivities;
+use std::io;
+
+pub const MAGIC: &[u8; 5] = b"YACHT";
+
+pub const CURRENT_VERSION: u16 = 14;
+
+const HEADER_LEN: usize = MAGIC.len() + 2;
+
+pub fn encode(activities: &AllActivities) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::from(&MAGIC[..]);
+    bytes.extend_from_slice(&CURRENT_VERSION.to_le_bytes());
+    bincode::serialize_into(&mut bytes, activities).map_err(io::Error::other)?;
+    Ok(bytes)
+}
+
+pub fn decode(bytes: &[u8]) -> io::Result<AllActivities> {
+    let (version, payload) = split_header(bytes)?;
+    decode_payload(version, payload)
+}
+
+/// Returns the format version of the file and its payload.
+pub fn split_header(bytes: &[u8]) -> io::Result<(u16, &[u8])> {
+    match bytes.strip_prefix(&MAGIC[..]) {
+        Some(rest) if rest.len() >= 2 => {
+            let version = u16::from_le_bytes([rest[0], rest[1]]);
+            Ok((version, &bytes[HEADER_LEN..]))
+        }
+        Some(_) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "truncated activities file header",
+        )),
+        None => Ok((0, bytes)),
+    }
+}
+
+/// Migration chain: reads the payload with the layout of its version and upgrades it
+/// step by step to the current one.
+fn decode_payload(version: u16, payload: &[u8]) -> io::Result<AllActivities> {
+    match version {
+        // version 1 only added the header, the payload is the same as in version 0.
+        // Versions 2 to 6 appended the `Weekdays`, `Quota`, `Interval`, `Monthly` and `Rrule`
+        // frequencies. Appended variants keep the index of the earlier ones, so versions 0
+        // to 6 all decode with the version 6 layout. The version in the header is what makes
+        // older builds refuse the newer files instead of mistaking them for corrupt ones.
+        0..=6 => Ok(deserialize::<v6::AllActivities>(payload)?
+            .upgrade()
+            .upgrade()
+            .upgrade()
+            .upgrade()
+            .upgrade()
+            .upgrade()
+            .upgrade()
+            .upgrade()),
+        // version 7 added the target and progress of quantitative activities
+        7 => Ok(deserialize::<v7::AllActivities>(payload)?
+            .upgrade()
+            .upgrade()
+            .upgrade()
+            .upgrade()
+            .upgrade()
+            .upgrade()
+            .upgrade()),
+        // version 8 added the kind of activity, to do or to avoid
+        8 => Ok(deserialize::<v8::AllActivities>(payload)?
+            .upgrade()
+            .upgrade()
+            .upgrade()
+            .upgrade()
+            .upgrade()
+            .upgrade()),
+        // version 9 keyed the activities by id instead of by name
+        9 => Ok(deserialize::<v9::AllActivities>(payload)?
+            .upgrade()
+            .upgrade()
+            .upgrade()
+            .upgrade()
+            .upgrade()),
+        // version 10 added the pauses of activities and of the whole tracker
+        10 => Ok(deserialize::<v10::AllActivities>(payload)?
+            .upgrade()
+            .upgrade()
+            .upgrade()
+            .upgrade()),
+        // version 11 added the skipped days
+        11 => Ok(deserialize::<v11::AllActivities>(payload)?
+            .upgrade()
+            .upgrade()
+            .upgrade()),
+        // version 12 added the local day and UTC offset of completions
+        12 => Ok(deserialize::<v12::AllActivities>(payload)?
+            .upgrade()
+            .upgrade()),
+        // version 13 kept removed activities with the day they were removed on
+        13 => Ok(deserialize::<v13::AllActivities>(payload)?.upgrade()),
+        // version 14 added the local day and UTC offset of progress
+        14 => deserialize(payload),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "activities file format version {} is newer than the supported version {}",
+                version, CURRENT_VERSION
+            ),
+        )),
+    }
+}
+
+fn deserialize<'a, T: serde::Deserialize<'a>>(payload: &'a [u8]) -> io::Result<T> {
+    bincode::deserialize(payload).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Layout of versions 0 to 6, before quantitative activities.
+mod v6 {
+    use super::v7;
+    use crate::activities_manager;
+    use chrono::Weekday;
+    use serde::Deserialize;
+    use std::collections::{BTreeMap, HashMap, HashSet};
+
+    #[derive(Deserialize)]
+    pub enum FrequencyType {
+        AllWeek,
+        WorkingDays,
+        WeekEnds,
+        Invalid,
+        Weekdays(WeekdaySet),
+        Quota { times: u8, period: QuotaPeriod },
+        Interval { days: u16, anchor: IntervalAnchor },
+        Monthly(MonthlyRule),
+        Rrule(String),
+    }
+
+    /// One bit per day starting with Monday.
+    #[derive(Deserialize)]
+    pub struct WeekdaySet(u8);
+
+    #[derive(Deserialize)]
+    pub enum QuotaPeriod {
+        Week,
+        Month,
+    }
+
+    #[derive(Deserialize)]
+    pub enum IntervalAnchor {
+        Start,
+        LastCompletion,
+    }
+
+    #[derive(Deserialize)]
+    pub enum MonthlyRule {
+        Day(u8),
+        Weekday { nth: u8, weekday: Weekday },
+        LastWeekday(Weekday),
+    }
+
+    impl From<FrequencyType> for activities_manager::FrequencyType {
+        fn from(frequency: FrequencyType) -> Self {
+            use activities_manager::FrequencyType as Live;
+            match frequency {
+                FrequencyType::AllWeek => Live::AllWeek,
+                FrequencyType::WorkingDays => Live::WorkingDays,
+                FrequencyType::WeekEnds => Live::WeekEnds,
+                FrequencyType::Invalid => Live::Invalid,
+                FrequencyType::Weekdays(WeekdaySet(bits)) => {
+                    Live::Weekdays(activities_manager::WeekdaySet::new(
+                        (0..7)
+                            .filter(|day| bits & (1 << day) != 0)
+                            .filter_map(|day| Weekday::try_from(day).ok()),
+                    ))
+                }
+                FrequencyType::Quota { times, period } => Live::Quota {
+                    times,
+                    period: match period {
+                        QuotaPeriod::Week => activities_manager::QuotaPeriod::Week,
+                        QuotaPeriod::Month => activities_manager::QuotaPeriod::Month,
+                    },
+                },
+                FrequencyType::Interval { days, anchor } => Live::Interval {
+                    days,
+                    anchor: match anchor {
+                        IntervalAnchor::Start => activities_manager::IntervalAnchor::Start,
+                        IntervalAnchor::LastCompletion => {
+                            activities_manager::IntervalAnchor::LastCompletion
+                        }
+                    },
+                },
+                FrequencyType::Monthly(rule) => Live::Monthly(match rule {
+                    MonthlyRule::Day(day) => activities_manager::MonthlyRule::Day(day),
+                    MonthlyRule::Weekday { nth, weekday } => {
+                        activities_manager::MonthlyRule::Weekday { nth, weekday }
+                    }
+                    MonthlyRule::LastWeekday(weekday) => {
+                        activities_manager::MonthlyRule::LastWeekday(weekday)
+                    }
+                }),
+                FrequencyType::Rrule(rule) => Live::Rrule(rule),
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct ActivityDetails {
+        start: i64,
+        frequency: FrequencyType,
+        completion_timestamps: HashSet<i64>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct AllActivities {
+        activities: HashMap<String, ActivityDetails>,
+    }
+
+    impl AllActivities {
+        pub fn upgrade(self) -> v7::AllActivities {
+            let activities = self
+                .activities
+                .into_iter()
+                .map(|(name, activity)| {
+                    let upgraded = v7::ActivityDetails {
+                        start: activity.start,
+                        frequency: activity.frequency,
+                        completion_timestamps: activity.completion_timestamps,
+                        target: None,
+                        progress: BTreeMap::new(),
+                    };
+                    (name, upgraded)
+                })
+                .collect();
+            v7::AllActivities { activities }
+        }
+    }
+}
+
+/// Layout of version 7, before activities to avoid.
+mod v7 {
+    use super::v6::FrequencyType;
+    use super::v8::{self, ActivityKind};
+    use crate::activities_manager;
+    use serde::Deserialize;
+    use std::collections::{BTreeMap, HashMap, HashSet};
+
+    #[derive(Deserialize)]
+    pub struct Target {
+        amount: f64,
+        unit: String,
+    }
+
+    impl From<Target> for activities_manager::Target {
+        fn from(target: Target) -> Self {
+            activities_manager::Target {
+                amount: target.amount,
+                unit: target.unit,
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct ActivityDetails {
+        pub(super) start: i64,
+        pub(super) frequency: FrequencyType,
+        pub(super) completion_timestamps: HashSet<i64>,
+        pub(super) target: Option<Target>,
+        pub(super) progress: BTreeMap<i64, f64>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct AllActivities {
+        pub(super) activities: HashMap<String, ActivityDetails>,
+    }
+
+    impl AllActivities {
+        pub fn upgrade(self) -> v8::AllActivities {
+            let activities = self
+                .activities
+                .into_iter()
+                .map(|(name, activity)| {
+                    let upgraded = v8::ActivityDetails {
+                        start: activity.start,
+                        frequency: activity.frequency,
+                        completion_timestamps: activity.completion_timestamps,
+                        target: activity.target,
+                        progress: activity.progress,
+                        kind: ActivityKind::Do,
+                    };
+                    (name, upgraded)
+                })
+                .collect();
+            v8::AllActivities { activities }
+        }
+    }
+}
+
+/// Layout of version 8, with the activities keyed by name.
+mod v8 {
+    use super::v6::FrequencyType;
+    use super::v7::Target;
+    use super::v9;
+    use crate::activities_manager;
+    use serde::Deserialize;
+    use std::collections::{BTreeMap, HashMap, HashSet};
+
+    #[derive(Deserialize)]
+    pub enum ActivityKind {
+        Do,
+        Avoid,
+    }
+
+    impl From<ActivityKind> for activities_manager::ActivityKind {
+        fn from(kind: ActivityKind) -> Self {
+            match kind {
+                ActivityKind::Do => activities_manager::ActivityKind::Do,
+                ActivityKind::Avoid => activities_manager::ActivityKind::Avoid,
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct ActivityDetails {
+        pub(super) start: i64,
+        pub(super) frequency: FrequencyType,
+        pub(super) completion_timestamps: HashSet<i64>,
+        pub(super) target: Option<Target>,
+        pub(super) progress: BTreeMap<i64, f64>,
+        pub(super) kind: ActivityKind,
+    }
+
+    #[derive(Deserialize)]
+    pub struct AllActivities {
+        pub(super) activities: HashMap<String, ActivityDetails>,
+    }
+
+    impl AllActivities {
+        /// Gives the activities ids in the order of their names, starting at 1.
+        pub fn upgrade(self) -> v9::AllActivities {
+            let mut activities: Vec<_> = self.activities.into_iter().collect();
+            activities.sort_by(|(a, _), (b, _)| a.cmp(b));
+            let mut upgraded = v9::AllActivities {
+                activities: HashMap::new(),
+                last_id: 0,
+            };
+            for (name, activity) in activities {
+                upgraded.last_id += 1;
+                let details = v9::ActivityDetails {
+                    name,
+                    start: activity.start,
+                    frequency: activity.frequency,
+                    completion_timestamps: activity.completion_timestamps,
+                    target: activity.target,
+                    progress: activity.progress,
+                    kind: activity.kind,
+                };
+                upgraded.activities.insert(upgraded.last_id, details);
+            }
+            upgraded
+        }
+    }
+}
+
+/// Layout of version 9, before pauses.
+mod v9 {
+    use super::v10;
+    use super::v6::FrequencyType;
+    use super::v7::Target;
+    use super::v8::ActivityKind;
+    use serde::Deserialize;
+    use std::collections::{BTreeMap, HashMap, HashSet};
+
+    #[derive(Deserialize)]
+    pub struct ActivityDetails {
+        pub(super) name: String,
+        pub(super) start: i64,
+        pub(super) frequency: FrequencyType,
+        pub(super) completion_timestamps: HashSet<i64>,
+        pub(super) target: Option<Target>,
+        pub(super) progress: BTreeMap<i64, f64>,
+        pub(super) kind: ActivityKind,
+    }
+
+    #[derive(Deserialize)]
+    pub struct AllActivities {
+        pub(super) activities: HashMap<u64, ActivityDetails>,
+        pub(super) last_id: u64,
+    }
+
+    impl AllActivities {
+        pub fn upgrade(self) -> v10::AllActivities {
+            let activities = self
+                .activities
+                .into_iter()
+                .map(|(id, activity)| {
+                    let upgraded = v10::ActivityDetails {
+                        name: activity.name,
+                        start: activity.start,
+                        frequency: activity.frequency,
+                        completion_timestamps: activity.completion_timestamps,
+                        target: activity.target,
+                        progress: activity.progress,
+                        kind: activity.kind,
+                        pauses: Vec::new(),
+                    };
+                    (id, upgraded)
+                })
+                .collect();
+            v10::AllActivities {
+                activities,
+                last_id: self.last_id,
+                pauses: Vec::new(),
+            }
+        }
+    }
+}
+
+/// Layout of version 10, before skipped days.
+mod v10 {
+    use super::v11;
+    use super::v6::FrequencyType;
+    use super::v7::Target;
+    use super::v8::ActivityKind;
+    use crate::activities_manager;
+    use chrono::NaiveDate;
+    use serde::Deserialize;
+    use std::collections::{BTreeMap, HashMap, HashSet};
+
+    #[derive(Deserialize)]
+    pub struct Pause {
+        from: NaiveDate,
+        until: NaiveDate,
+    }
+
+    impl From<Pause> for activities_manager::Pause {
+        fn from(pause: Pause) -> Self {
+            activities_manager::Pause {
+                from: pause.from,
+                until: pause.until,
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct ActivityDetails {
+        pub(super) name: String,
+        pub(super) start: i64,
+        pub(super) frequency: FrequencyType,
+        pub(super) completion_timestamps: HashSet<i64>,
+        pub(super) target: Option<Target>,
+        pub(super) progress: BTreeMap<i64, f64>,
+        pub(super) kind: ActivityKind,
+        pub(super) pauses: Vec<Pause>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct AllActivities {
+        pub(super) activities: HashMap<u64, ActivityDetails>,
+        pub(super) last_id: u64,
+        pub(super) pauses: Vec<Pause>,
+    }
+
+    impl AllActivities {
+        pub fn upgrade(self) -> v11::AllActivities {
+            let activities = self
+                .activities
+                .into_iter()
+                .map(|(id, activity)| {
+                    let upgraded = v11::ActivityDetails {
+                        name: activity.name,
+                        start: activity.start,
+                        frequency: activity.frequency,
+                        completion_timestamps: activity.completion_timestamps,
+                        target: activity.target,
+                        progress: activity.progress,
+                        kind: activity.kind,
+                        pauses: activity.pauses,
+                        skips: BTreeMap::new(),
+                    };
+                    (id, upgraded)
+                })
+                .collect();
+            v11::AllActivities {
+                activities,
+                last_id: self.last_id,
+                pauses: self.pauses,
+            }
+        }
+    }
+}
+
+/// Layout of version 11, before the days completions were logged for.
+mod v11 {
+    use super::v10::Pause;
+    use super::v12;
+    use super::v6::FrequencyType;
+    use super::v7::Target;
+    use super::v8::ActivityKind;
+    use chrono::NaiveDate;
+    use serde::Deserialize;
+    use std::collections::{BTreeMap, HashMap, HashSet};
+
+    #[derive(Deserialize)]
+    pub struct ActivityDetails {
+        pub(super) name: String,
+        pub(super) start: i64,
+        pub(super) frequency: FrequencyType,
+        pub(super) completion_timestamps: HashSet<i64>,
+        pub(super) target: Option<Target>,
+        pub(super) progress: BTreeMap<i64, f64>,
+        pub(super) kind: ActivityKind,
+        pub(super) pauses: Vec<Pause>,
+        pub(super) skips: BTreeMap<NaiveDate, String>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct AllActivities {
+        pub(super) activities: HashMap<u64, ActivityDetails>,
+        pub(super) last_id: u64,
+        pub(super) pauses: Vec<Pause>,
+    }
+
+    impl AllActivities {
+        /// Completions logged before count for the local day of their timestamp.
+        pub fn upgrade(self) -> v12::AllActivities {
+            let activities = self
+                .activities
+                .into_iter()
+                .map(|(id, activity)| {
+                    let upgraded = v12::ActivityDetails {
+                        name: activity.name,
+                        start: activity.start,
+                        frequency: activity.frequency,
+                        completion_timestamps: activity.completion_timestamps,
+                        target: activity.target,
+                        progress: activity.progress,
+                        kind: activity.kind,
+                        pauses: activity.pauses,
+                        skips: activity.skips,
+                        completion_days: HashMap::new(),
+                    };
+                    (id, upgraded)
+                })
+                .collect();
+            v12::AllActivities {
+                activities,
+                last_id: self.last_id,
+                pauses: self.pauses,
+            }
+        }
+    }
+}
+
+/// Layout of version 12, before removed activities were kept.
+mod v12 {
+    use super::v10::Pause;
+    use super::v13;
+    use super::v6::FrequencyType;
+    use super::v7::Target;
+    use super::v8::ActivityKind;
+    use crate::activities_manager;
+    use chrono::NaiveDate;
+    use serde::Deserialize;
+    use std::collections::{BTreeMap, HashMap, HashSet};
+
+    #[derive(Deserialize)]
+    pub struct LocalDay {
+        date: NaiveDate,
+        offset: i32,
+    }
+
+    impl From<LocalDay> for activities_manager::LocalDay {
+        fn from(day: LocalDay) -> Self {
+            activities_manager::LocalDay {
+                date: day.date,
+                offset: day.offset,
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct ActivityDetails {
+        pub(super) name: String,
+        pub(super) start: i64,
+        pub(super) frequency: FrequencyType,
+        pub(super) completion_timestamps: HashSet<i64>,
+        pub(super) target: Option<Target>,
+        pub(super) progress: BTreeMap<i64, f64>,
+        pub(super) kind: ActivityKind,
+        pub(super) pauses: Vec<Pause>,
+        pub(super) skips: BTreeMap<NaiveDate, String>,
+        pub(super) completion_days: HashMap<i64, LocalDay>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct AllActivities {
+        pub(super) activities: HashMap<u64, ActivityDetails>,
+        pub(super) last_id: u64,
+        pub(super) pauses: Vec<Pause>,
+    }
+
+    impl AllActivities {
+        /// Activities removed before were dropped, every one left is a current one.
+        pub fn upgrade(self) -> v13::AllActivities {
+            v13::AllActivities {
+                activities: self
+                    .activities
+                    .into_iter()
+                    .map(|(id, activity)| {
+                        let details = v13::ActivityDetails {
+                            name: activity.name,
+                            start: activity.start,
+                            frequency: activity.frequency,
+                            completion_timestamps: activity.completion_timestamps,
+                            target: activity.target,
+                            progress: activity.progress,
+                            kind: activity.kind,
+                            pauses: activity.pauses,
+                            skips: activity.skips,
+                            completion_days: activity.completion_days,
+                            removed: None,
+                        };
+                        (id, details)
+                    })
+                    .collect(),
+                last_id: self.last_id,
+                pauses: self.pauses,
+            }
+        }
+    }
+}
+
+/// Layout of version 13, before the local day of progress.
+mod v13 {
+    use super::v10::Pause;
+    use super::v12::LocalDay;
+    use super::v6::FrequencyType;
+    use super::v7::Target;
+    use super::v8::ActivityKind;
+    use crate::activities_manager;
+    use chrono::NaiveDate;
+    use serde::Deserialize;
+    use std::collections::{BTreeMap, HashMap, HashSet};
+
+    #[derive(Deserialize)]
+    pub struct ActivityDetails {
+        pub(super) name: String,
+        pub(super) start: i64,
+        pub(super) frequency: FrequencyType,
+        pub(super) completion_timestamps: HashSet<i64>,
+        pub(super) target: Option<Target>,
+        pub(super) progress: BTreeMap<i64, f64>,
+        pub(super) kind: ActivityKind,
+        pub(super) pauses: Vec<Pause>,
+        pub(super) skips: BTreeMap<NaiveDate, String>,
+        pub(super) completion_days: HashMap<i64, LocalDay>,
+        pub(super) removed: Option<NaiveDate>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct AllActivities {
+        pub(super) activities: HashMap<u64, ActivityDetails>,
+        pub(super) last_id: u64,
+        pub(super) pauses: Vec<Pause>,
+    }
+
+    impl AllActivities {
+        /// Progress logged before counts for the local day of its timestamp.
+        pub fn upgrade(self) -> activities_manager::AllActivities {
+            let mut upgraded = activities_manager::AllActivities::new();
+            upgraded.last_id = self.last_id;
+            upgraded.pauses = self.pauses.into_iter().map(Into::into).collect();
+            for (id, activity) in self.activities {
+                let mut details =
+                    activities_manager::ActivityDetails::new_activity(activity.frequency.into())
+                        .with_target(activity.target.map(Into::into))
+                        .with_kind(activity.kind.into());
+                details.name = activity.name;
+                details.start = activity.start;
+                details.completion_timestamps = activity.completion_timestamps;
+                details.progress = activity.progress;
+                details.pauses = activity.pauses.into_iter().map(Into::into).collect();
+                details.skips = activity.skips;
+                details.completion_days = activity
+                    .completion_days
+                    .into_iter()
+                    .map(|(timestamp, day)| (timestamp, day.into()))
+                    .collect();
+                details.removed = activity.removed;
+                upgraded.activities.insert(id, details);
+            }
+            upgraded
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use crate::activities_manager::{ActivityDetails, AllActivities, FrequencyType, LocalDay, Pause};
+use crate::data_location;
+use crate::file_format;
+use crate::safe_file::{self, Loaded};
+use crate::storage::{self, Storage};
+use chrono::NaiveDate;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Storage in a single bincode file, see [`file_format`] and [`safe_file`].
+#[derive(Debug)]
+pub struct FileStorage {
+    path: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(path: PathBuf) -> Self {
+        FileStorage { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Applies a single change to what is on disk, holding the lock so that changes made by
+    /// other processes in the meantime are kept. Fails if the file is unreadable rather than
+    /// applying the change to a backup.
+    fn modify<T>(&mut self, change: impl FnOnce(&mut AllActivities) -> T) -> io::Result<T> {
+        data_location::ensure_parent_dir(&self.path)?;
+        let _lock = safe_file::lock(&self.path)?;
+        let mut activities = match self.load() {
+            // a change applied to a backup would silently drop what the unreadable file has
+            Ok(Loaded {
+                recovered_from: Some(backup),
+                ..
+            }) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "{} is unreadable and was left as it is, {} is the newest readable backup",
+                        self.path.display(),
+                        backup.display()
+                    ),
+                ))
+            }
+            Ok(loaded) => loaded.value,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => AllActivities::new(),
+            Err(e) => return Err(e),
+        };
+        let changed = change(&mut activities);
+        self.write(&activities)?;
+        Ok(changed)
+    }
+
+    // callers hold the lock
+    fn write(&self, activities: &AllActivities) -> io::Result<()> {
+        let bytes = file_format::encode(activities)?;
+        // only readable versions are worth keeping as backups
+        if fs::read(&self.path).is_ok_and(|current| file_format::decode(&current).is_ok()) {
+            safe_file::rotate_backups(&self.path, safe_file::BACKUP_COUNT)?;
+        }
+        safe_file::write_atomic(&self.path, &bytes)
+    }
+}
+
+impl Storage for FileStorage {
+    fn load(&self) -> io::Result<Loaded<AllActivities>> {
+        safe_file::load_with_fallback(&self.path, safe_file::BACKUP_COUNT, file_format::decode)
+    }
+
+    fn save(&mut self, activities: &AllActivities) -> io::Result<()> {
+        data_location::ensure_parent_dir(&self.path)?;
+        let _lock = safe_file::lock(&self.path)?;
+        self.write(activities)
+    }
+
+    fn exists(&self) -> bool {
+        safe_file::any_version_exists(&self.path, safe_file::BACKUP_COUNT)
+    }
+
+    fn insert_activity(&mut self, activity: &ActivityDetails) -> io::Result<u64> {
+        self.modify(|all| storage::insert_activity(all, activity))
+    }
+
+    fn edit_activity(&mut self, id: u64, name: &str, frequency: &FrequencyType) -> io::Result<()> {
+        self.modify(|all| storage::edit_activity(all, id, name, frequency))
+    }
+
+    fn remove_activity(&mut self, id: u64, date: NaiveDate) -> io::Result<()> {
+        self.modify(|all| storage::remove_activity(all, id, date))
+    }
+
+    fn delete_activity(&mut self, id: u64) -> io::Result<()> {
+        self.modify(|all| storage::delete_activity(all, id))
+    }
+
+    fn restore_activity(&mut self, id: u64, activity: &ActivityDetails) -> io::Result<()> {
+        self.modify(|all| storage::restore_activity(all, id, activity))
+    }
+
+    fn add_completion(&mut self, id: u64, timestamp: i64, day: LocalDay) -> io::Result<()> {
+        self.modify(|all| storage::add_completion(all, id, timestamp, day))
+    }
+
+    fn remove_completion(&mut self, id: u64, timestamp: i64) -> io::Result<()> {
+        self.modify(|all| storage::remove_completion(all, id, timestamp))
+    }
+
+    fn add_progress(
+        &mut self,
+        id: u64,
+        timestamp: i64,
+        amount: f64,
+        day: LocalDay,
+    ) -> io::Result<()> {
+        self.modify(|all| storage::add_progress(all, id, timestamp, amount, day))
+    }
+
+    fn remove_progress(&mut self, id: u64, timestamp: i64, amount: f64) -> io::Result<()> {
+        self.modify(|all| storage::remove_progress(all, id, timestamp, amount))
+    }
+
+    fn add_pause(&mut self, id: Option<u64>, pause: &Pause) -> io::Result<()> {
+        self.modify(|all| storage::add_pause(all, id, pause))
+    }
+
+    fn remove_pause(&mut self, id: Option<u64>, pause: &Pause) -> io::Result<()> {
+        self.modify(|all| storage::remove_pause(all, id, pause))
+    }
+
+    fn add_skip(&mut self, id: u64, date: NaiveDate, reason: &str) -> io::Result<()> {
+        self.modify(|all| storage::add_skip(all, id, date, reason))
+    }
+
+    fn remove_skip(&mut self, id: u64, date: NaiveDate) -> io::Result<()> {
+        self.modify(|all| storage::remove_skip(all, id, date))
+    }
+
+    // kept next to the data file like the history, so the file format stays as it is
+    fn calendar_id(&mut self) -> io::Result<String> {
+        let path = safe_file::with_suffix(&self.path, "calendar-id");
+        data_location::ensure_parent_dir(&self.path)?;
+        let _lock = safe_file::lock(&self.path)?;
+        match fs::read_to_string(&path) {
+            Ok(id) if !id.trim().is_empty() => Ok(id.trim().to_string()),
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => {
+                let id = storage::new_calendar_id();
+                safe_file::write_atomic(&path, id.as_bytes())?;
+                Ok(id)
+            }
+        }
+    }
+}
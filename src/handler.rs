@@ -1,5 +1,6 @@
+use crate::activities_manager::ActivityKind;
 use crate::app::{App, AppResult, InputMode};
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
 /// Handles the key events and updates the state of [`App`].
 pub fn handle_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
@@ -18,33 +19,100 @@ pub fn handle_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
         KeyCode::Char('d') | KeyCode::Char('D') if app.input_mode == InputMode::Inactive => {
             app.set_activity_done(app.selected);
         }
+        KeyCode::Char('r')
+            if app.input_mode == InputMode::Inactive
+                && key_event.modifiers.contains(KeyModifiers::CONTROL) =>
+        {
+            app.redo();
+        }
         KeyCode::Char('r') | KeyCode::Char('R') if app.input_mode == InputMode::Inactive => {
             app.remove_activity(app.selected);
         }
+        KeyCode::Char('u') | KeyCode::Char('U') if app.input_mode == InputMode::Inactive => {
+            app.undo();
+        }
 
-        KeyCode::Up if app.todays_activities.is_empty() == false => {
-            if app.selected > 0 {
-                app.selected -= 1;
-            }
+        KeyCode::Char('[') | KeyCode::Left if app.input_mode == InputMode::Inactive => {
+            app.previous_day();
         }
-        KeyCode::Down if app.todays_activities.is_empty() == false => {
-            if app.selected < app.todays_activities.len() - 1 {
-                app.selected += 1;
-            }
+        KeyCode::Char(']') | KeyCode::Right if app.input_mode == InputMode::Inactive => {
+            app.next_day();
+        }
+
+        KeyCode::Up if !app.todays_activities.is_empty() && app.selected > 0 => {
+            app.selected -= 1;
+        }
+        KeyCode::Down
+            if !app.todays_activities.is_empty()
+                && app.selected < app.todays_activities.len() - 1 =>
+        {
+            app.selected += 1;
         }
 
         KeyCode::Char('n') | KeyCode::Char('N') if app.input_mode == InputMode::Inactive => {
+            app.new_activity_kind = ActivityKind::Do;
+            app.input_mode = InputMode::ActiveName;
+        }
+        KeyCode::Char('a') | KeyCode::Char('A') if app.input_mode == InputMode::Inactive => {
+            app.new_activity_kind = ActivityKind::Avoid;
             app.input_mode = InputMode::ActiveName;
         }
 
-        KeyCode::Char(c) if app.input_mode == InputMode::ActiveName => {
+        KeyCode::Char('s') | KeyCode::Char('S') if app.input_mode == InputMode::Inactive => {
+            app.start_skip(app.selected);
+        }
+        KeyCode::Char('p') | KeyCode::Char('P') if app.input_mode == InputMode::Inactive => {
+            app.start_pause(Some(app.selected));
+        }
+        KeyCode::Char('v') | KeyCode::Char('V') if app.input_mode == InputMode::Inactive => {
+            app.start_pause(None);
+        }
+
+        KeyCode::Char('e') | KeyCode::Char('E') if app.input_mode == InputMode::Inactive => {
+            app.start_edit(app.selected);
+        }
+
+        KeyCode::Char(c)
+            if matches!(
+                app.input_mode,
+                InputMode::ActiveName | InputMode::ActiveEditName | InputMode::ActiveSkip
+            ) =>
+        {
             app.enter_char(c);
         }
 
-        KeyCode::Char(c) if app.input_mode == InputMode::ActiveFrequency => match c {
-            'a' | 'w' | 'd' | 'e' => app.enter_char(c),
-            _ => {}
-        },
+        // codes, day names like `mon,wed,fri`, quotas like `3/w` and rules like
+        // `rrule:freq=monthly;byday=-1su`
+        KeyCode::Char(c)
+            if matches!(
+                app.input_mode,
+                InputMode::ActiveFrequency | InputMode::ActiveEditFrequency
+            ) && (c.is_ascii_alphanumeric()
+                || [',', ' ', '/', '=', ';', ':', '-', '+'].contains(&c)) =>
+        {
+            app.enter_char(c)
+        }
+
+        // targets like `2 km` and amounts like `1.2`
+        KeyCode::Char(c)
+            if app.input_mode == InputMode::ActiveTarget
+                && (c.is_ascii_alphanumeric() || [' ', '.'].contains(&c)) =>
+        {
+            app.enter_char(c)
+        }
+        KeyCode::Char(c)
+            if app.input_mode == InputMode::ActiveProgress && (c.is_ascii_digit() || c == '.') =>
+        {
+            app.enter_char(c)
+        }
+
+        // dates like `2024-01-27` and ranges like `2024-01-20..2024-01-27`
+        KeyCode::Char(c)
+            if app.input_mode == InputMode::ActivePause
+                && (c.is_ascii_digit() || "-.".contains(c)) =>
+        {
+            app.enter_char(c)
+        }
 
         KeyCode::Backspace if app.input_mode != InputMode::Inactive => {
             app.delete_char();
@@ -59,7 +127,47 @@ pub fn handle_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
             }
             InputMode::ActiveFrequency => {
                 app.convert_activity_frequency();
-                app.add_new_activity();
+                app.input_mode = match app.new_activity_frequency.is_valid() {
+                    // activities to avoid have no target
+                    true if app.new_activity_kind == ActivityKind::Avoid => {
+                        app.add_new_activity();
+                        InputMode::Inactive
+                    }
+                    true => InputMode::ActiveTarget,
+                    false => InputMode::Inactive,
+                };
+                app.input.clear();
+                app.cursor_position = 0;
+            }
+            InputMode::ActiveTarget => {
+                if app.convert_activity_target() {
+                    app.add_new_activity();
+                }
+                app.input.clear();
+                app.cursor_position = 0;
+                app.input_mode = InputMode::Inactive;
+            }
+            InputMode::ActiveEditName => app.edit_frequency(),
+            InputMode::ActiveEditFrequency => {
+                app.edit_activity();
+                app.input.clear();
+                app.cursor_position = 0;
+                app.input_mode = InputMode::Inactive;
+            }
+            InputMode::ActiveSkip => {
+                app.skip_activity();
+                app.input.clear();
+                app.cursor_position = 0;
+                app.input_mode = InputMode::Inactive;
+            }
+            InputMode::ActivePause => {
+                app.pause();
+                app.input.clear();
+                app.cursor_position = 0;
+                app.input_mode = InputMode::Inactive;
+            }
+            InputMode::ActiveProgress => {
+                app.log_progress();
                 app.input.clear();
                 app.cursor_position = 0;
                 app.input_mode = InputMode::Inactive;
@@ -0,0 +1,326 @@
+//! Undo and redo of the changes made from the TUI.
+//!
+//! Each change is kept as an [`Operation`] with what it takes to revert it and to apply it
+//! again, by id: an undone removal puts the activity back under its old id with its whole
+//! history. The operations are saved next to the data file, so they outlive a restart.
+//!
+//! Unlike the changes to the activities, the history isn't saved under the lock of the data
+//! file: each instance saves its own as a whole, and the last one to save it keeps it.
+
+use crate::activities_manager::{ActivityDetails, AllActivities, FrequencyType, LocalDay};
+use crate::data_location;
+use crate::safe_file;
+use crate::storage::{self, Storage};
+use crate::time_utils;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Number of operations that can be undone.
+pub const HISTORY_LIMIT: usize = 100;
+
+/// A change that can be undone and redone.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Operation {
+    /// A completion, or a slip of an activity to avoid.
+    Done {
+        id: u64,
+        timestamp: i64,
+        day: LocalDay,
+    },
+    Add {
+        id: u64,
+        activity: ActivityDetails,
+    },
+    Remove {
+        id: u64,
+        activity: ActivityDetails,
+    },
+    /// The name and frequency before and after an edit.
+    Edit {
+        id: u64,
+        before: (String, FrequencyType),
+        after: (String, FrequencyType),
+    },
+    /// Completions, or slips, taken back from a past day.
+    Cleared {
+        id: u64,
+        completions: Vec<(i64, LocalDay)>,
+    },
+    /// Progress of a quantitative activity and the day it was logged for, with the
+    /// completion logged with it when it reached the target.
+    Progress {
+        id: u64,
+        timestamp: i64,
+        amount: f64,
+        day: LocalDay,
+        completion: Option<LocalDay>,
+    },
+    /// A skip, with the reason of the skip it replaced.
+    Skip {
+        id: u64,
+        date: NaiveDate,
+        reason: String,
+        replaced: Option<String>,
+    },
+}
+
+impl Operation {
+    /// The completion logged at `timestamp`, with the day it counts for.
+    pub fn done(all: &AllActivities, id: u64, timestamp: i64) -> Operation {
+        let day = all.activities.get(&id).map_or_else(
+            || LocalDay::of(timestamp),
+            |activity| activity.completion_day(timestamp),
+        );
+        Operation::Done { id, timestamp, day }
+    }
+
+    /// What the operation did to which activity, like `READ is set done`.
+    pub fn describe(&self, all: &AllActivities) -> String {
+        let name = |id: &u64| {
+            all.activities
+                .get(id)
+                .map(|activity| activity.name.clone())
+                .unwrap_or_default()
+        };
+        match self {
+            Operation::Done { id, .. } => format!("{} is set done", name(id)),
+            Operation::Add { activity, .. } => format!("{} is added", activity.name),
+            Operation::Remove { activity, .. } => format!("{} is removed", activity.name),
+            Operation::Edit { before, .. } => format!("{} is edited", before.0),
+            Operation::Cleared { id, .. } => format!("{} is set not done", name(id)),
+            Operation::Progress { id, .. } => format!("{} progress is logged", name(id)),
+            Operation::Skip { id, .. } => format!("{} is skipped", name(id)),
+        }
+    }
+
+    /// Applies the operation again, or reverts it if `forward` is false.
+    fn apply(
+        &self,
+        all: &mut AllActivities,
+        storage: &mut dyn Storage,
+        forward: bool,
+    ) -> Result<(), &'static str> {
+        let saved = |result: io::Result<()>| result.map_err(|_| "Failed to save activities");
+        match (self, forward) {
+            (Operation::Done { id, timestamp, day }, done) => {
+                set_done(all, storage, *id, &[(*timestamp, *day)], done)?
+            }
+            (Operation::Cleared { id, completions }, cleared) => {
+                set_done(all, storage, *id, completions, !cleared)?
+            }
+            (Operation::Add { id, activity }, true)
+            | (Operation::Remove { id, activity }, false) => {
+                // a removed activity is kept, and comes back as it was before the removal
+                if is_current(all, *id) || all.is_in_activities(activity.name.clone()) {
+                    return Err("Activity Already Exists");
+                }
+                saved(storage.restore_activity(*id, activity))?;
+                all.activities.insert(*id, activity.clone());
+                all.last_id = all.last_id.max(*id);
+            }
+            (Operation::Add { id, .. }, false) => {
+                if !is_current(all, *id) {
+                    return Err("Activity Not Found");
+                }
+                saved(storage.delete_activity(*id))?;
+                all.activities.remove(id);
+            }
+            (Operation::Remove { id, .. }, true) => {
+                if !is_current(all, *id) {
+                    return Err("Activity Not Found");
+                }
+                let today = time_utils::current_date();
+                saved(storage.remove_activity(*id, today))?;
+                if let Some(activity) = all.activities.get_mut(id) {
+                    activity.removed = Some(today);
+                }
+            }
+            (Operation::Edit { id, before, after }, forward) => {
+                let (name, frequency) = match forward {
+                    true => after,
+                    false => before,
+                };
+                if all.id_of(name).is_some_and(|other| other != *id) {
+                    return Err("Activity Already Exists");
+                }
+                let activity = all.activities.get_mut(id).ok_or("Activity Not Found")?;
+                saved(storage.edit_activity(*id, name, frequency))?;
+                activity.name = name.clone();
+                activity.frequency = frequency.clone();
+            }
+            (
+                Operation::Progress {
+                    id,
+                    timestamp,
+                    amount,
+                    day,
+                    completion,
+                },
+                logged,
+            ) => {
+                if !all.activities.contains_key(id) {
+                    return Err("Activity Not Found");
+                }
+                match logged {
+                    true => {
+                        saved(storage.add_progress(*id, *timestamp, *amount, *day))?;
+                        storage::add_progress(all, *id, *timestamp, *amount, *day);
+                    }
+                    false => {
+                        saved(storage.remove_progress(*id, *timestamp, *amount))?;
+                        storage::remove_progress(all, *id, *timestamp, *amount);
+                    }
+                }
+                if let Some(day) = completion {
+                    set_done(all, storage, *id, &[(*timestamp, *day)], logged)?;
+                }
+            }
+            (
+                Operation::Skip {
+                    id,
+                    date,
+                    reason,
+                    replaced,
+                },
+                skipped,
+            ) => {
+                if !all.activities.contains_key(id) {
+                    return Err("Activity Not Found");
+                }
+                let reason = match skipped {
+                    true => Some(reason),
+                    false => replaced.as_ref(),
+                };
+                match reason {
+                    Some(reason) => {
+                        saved(storage.add_skip(*id, *date, reason))?;
+                        storage::add_skip(all, *id, *date, reason);
+                    }
+                    None => {
+                        saved(storage.remove_skip(*id, *date))?;
+                        storage::remove_skip(all, *id, *date);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_current(all: &AllActivities, id: u64) -> bool {
+    all.activities
+        .get(&id)
+        .is_some_and(|activity| !activity.is_removed())
+}
+
+/// Adds the completions of an activity, or removes them if `done` is false.
+fn set_done(
+    all: &mut AllActivities,
+    storage: &mut dyn Storage,
+    id: u64,
+    completions: &[(i64, LocalDay)],
+    done: bool,
+) -> Result<(), &'static str> {
+    let activity = all.activities.get_mut(&id).ok_or("Activity Not Found")?;
+    for (timestamp, day) in completions {
+        let saved = match done {
+            true => storage.add_completion(id, *timestamp, *day),
+            false => storage.remove_completion(id, *timestamp),
+        };
+        saved.map_err(|_| "Failed to save activities")?;
+        match done {
+            true => activity.add_completion(*timestamp, *day),
+            false => activity.remove_completion(*timestamp),
+        };
+    }
+    Ok(())
+}
+
+/// Operations to undo, the latest last, and the undone ones to redo.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct History {
+    undo: Vec<Operation>,
+    redo: Vec<Operation>,
+    /// File the history is saved to, none to keep it in memory only.
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+/// Path of the history kept next to the data file at `path`.
+pub fn history_path(path: &Path) -> PathBuf {
+    safe_file::with_suffix(path, "history")
+}
+
+impl History {
+    /// Loads the history saved at `path`, starting a new one if it is missing or unreadable.
+    pub fn load(path: PathBuf) -> History {
+        let mut history = fs::read(&path)
+            .ok()
+            .and_then(|bytes| bincode::deserialize::<History>(&bytes).ok())
+            .unwrap_or_default();
+        history.path = Some(path);
+        history
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Records a change that was just made, which can't be followed by a redo anymore.
+    pub fn record(&mut self, operation: Operation) {
+        self.undo.push(operation);
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+        self.save();
+    }
+
+    /// Reverts the latest change and returns it. A change that can't be reverted anymore,
+    /// like a completion of an activity removed elsewhere, is dropped.
+    pub fn undo(
+        &mut self,
+        all: &mut AllActivities,
+        storage: &mut dyn Storage,
+    ) -> Result<Operation, &'static str> {
+        let operation = self.undo.pop().ok_or("Nothing To Undo")?;
+        let result = operation.apply(all, storage, false);
+        if result.is_ok() {
+            self.redo.push(operation.clone());
+        }
+        self.save();
+        result.map(|_| operation)
+    }
+
+    /// Applies the latest undone change again and returns it.
+    pub fn redo(
+        &mut self,
+        all: &mut AllActivities,
+        storage: &mut dyn Storage,
+    ) -> Result<Operation, &'static str> {
+        let operation = self.redo.pop().ok_or("Nothing To Redo")?;
+        let result = operation.apply(all, storage, true);
+        if result.is_ok() {
+            self.undo.push(operation.clone());
+        }
+        self.save();
+        result.map(|_| operation)
+    }
+
+    // the history is a convenience, failing to save it doesn't undo the change
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let _ = data_location::ensure_parent_dir(path)
+            .and_then(|_| bincode::serialize(self).map_err(io::Error::other))
+            .and_then(|bytes| safe_file::write_atomic(path, &bytes));
+    }
+}
@@ -0,0 +1,221 @@
+//! iCalendar export, with a recurring all-day event per activity.
+//!
+//! Completed occurrences override their day of the series with a `✓` in the summary, or a
+//! `✗` for the slips of activities to avoid, and completions on unscheduled days, or of
+//! activities without fixed days and so without a series, are single events of their own.
+
+use crate::activities_manager::{
+    ActivityDetails, ActivityKind, AllActivities, FrequencyType, IntervalAnchor, MonthlyRule,
+};
+use crate::time_utils;
+use chrono::{DateTime, NaiveDate, Utc, Weekday};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// Longest line allowed by RFC 5545, in octets.
+const MAX_LINE: usize = 75;
+
+/// Days after its start searched for the first occurrence of a series.
+const FIRST_OCCURRENCE_SEARCH_DAYS: usize = 4 * 366;
+
+/// Prefix of the summary of completed occurrences.
+pub const DONE: &str = "✓ ";
+
+/// Prefix of the summary of occurrences with a slip, for activities to avoid.
+pub const SLIPPED: &str = "✗ ";
+
+/// Property set to `AVOID` on the series of activities to avoid.
+pub const KIND: &str = "X-YACHT-KIND";
+
+/// Calendar with every activity, `now` being used for the `DTSTAMP` of each event and
+/// `calendar_id`, see [`crate::storage::Storage::calendar_id`], for their `UID`.
+pub fn calendar(all: &AllActivities, calendar_id: &str, now: DateTime<Utc>) -> String {
+    let stamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        String::from("PRODID:-//yacht//habits//EN"),
+        String::from("CALSCALE:GREGORIAN"),
+        String::from("X-WR-CALNAME:yacht"),
+    ];
+
+    // a calendar only has the current activities, replacing with it keeps the removed ones
+    let mut activities: Vec<(&u64, &ActivityDetails)> = all.current().collect();
+    activities.sort_by(|(_, a), (_, b)| a.name.cmp(&b.name));
+    for (id, activity) in activities {
+        let name = &activity.name;
+        let uid = uid(*id, calendar_id);
+        let start = first_occurrence(activity);
+        let rule = rrule(&activity.frequency);
+        if let Some(rule) = &rule {
+            let mut properties = vec![
+                format!("SUMMARY:{}", escape(name)),
+                format!("RRULE:{}", rule),
+            ];
+            if activity.kind == ActivityKind::Avoid {
+                properties.push(format!("{}:AVOID", KIND));
+            }
+            lines.extend(event(&uid, &stamp, start, properties));
+        }
+        let (mark, logged) = match activity.kind {
+            ActivityKind::Do => (DONE, "Done"),
+            ActivityKind::Avoid => (SLIPPED, "Slipped"),
+        };
+
+        // completion times of each local day, to override the matching occurrence
+        let mut days: BTreeMap<NaiveDate, Vec<i64>> = BTreeMap::new();
+        for timestamp in &activity.completion_timestamps {
+            days.entry(activity.completion_day(*timestamp).date)
+                .or_default()
+                .push(*timestamp);
+        }
+        for (date, mut timestamps) in days {
+            timestamps.sort();
+            // only an occurrence of the series can be overridden
+            let scheduled = rule.is_some() && date >= start && activity.is_due_on(date);
+            // times after midnight that count for the day before, with a later day start,
+            // keep their own date
+            let times: Vec<String> = timestamps
+                .iter()
+                .map(|t| {
+                    let time = activity.completion_day(*t).time_of(*t);
+                    match time.date_naive() == date {
+                        true => time.format("%H:%M:%S").to_string(),
+                        false => time.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    }
+                })
+                .collect();
+            let mut properties = vec![
+                format!("SUMMARY:{}{}", mark, escape(name)),
+                format!("DESCRIPTION:{} at {}", logged, escape(&times.join(", "))),
+            ];
+            let uid = match scheduled {
+                true => {
+                    properties.push(format!("RECURRENCE-ID;VALUE=DATE:{}", ics_date(date)));
+                    uid.clone()
+                }
+                false => format!("{}-{}", uid, ics_date(date)),
+            };
+            lines.extend(event(&uid, &stamp, date, properties));
+        }
+    }
+    lines.push(String::from("END:VCALENDAR"));
+
+    let mut text = String::new();
+    for line in lines {
+        fold(&line, &mut text);
+    }
+    text
+}
+
+pub fn write_ics<W: Write>(
+    all: &AllActivities,
+    calendar_id: &str,
+    mut writer: W,
+) -> io::Result<()> {
+    writer.write_all(calendar(all, calendar_id, Utc::now()).as_bytes())?;
+    writer.flush()
+}
+
+/// Recurrence rule of a frequency, none for [`FrequencyType::Invalid`], quotas and intervals
+/// following the last completion, which have no fixed days. Their completions are still
+/// exported.
+pub fn rrule(frequency: &FrequencyType) -> Option<String> {
+    match frequency {
+        FrequencyType::AllWeek => Some(String::from("FREQ=DAILY")),
+        FrequencyType::WorkingDays => Some(String::from("FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR")),
+        FrequencyType::WeekEnds => Some(String::from("FREQ=WEEKLY;BYDAY=SA,SU")),
+        FrequencyType::Interval {
+            days,
+            anchor: IntervalAnchor::Start,
+        } => Some(format!("FREQ=DAILY;INTERVAL={}", days)),
+        FrequencyType::Monthly(rule) => Some(format!("FREQ=MONTHLY;{}", monthly_rule(rule))),
+        FrequencyType::Rrule(rule) => Some(rule.clone()),
+        FrequencyType::Invalid
+        | FrequencyType::Quota { .. }
+        | FrequencyType::Interval {
+            anchor: IntervalAnchor::LastCompletion,
+            ..
+        } => None,
+        FrequencyType::Weekdays(set) => {
+            let days: Vec<String> = set.days().iter().map(byday).collect();
+            Some(format!("FREQ=WEEKLY;BYDAY={}", days.join(",")))
+        }
+    }
+}
+
+fn monthly_rule(rule: &MonthlyRule) -> String {
+    match rule {
+        // the last of the candidate days the month has, as days past its end fall on its last day
+        MonthlyRule::Day(day) if *day > 28 => {
+            let days: Vec<String> = (28..=*day).map(|day| day.to_string()).collect();
+            format!("BYMONTHDAY={};BYSETPOS=-1", days.join(","))
+        }
+        MonthlyRule::Day(day) => format!("BYMONTHDAY={}", day),
+        MonthlyRule::Weekday { nth, weekday } => format!("BYDAY={}{}", nth, byday(weekday)),
+        MonthlyRule::LastWeekday(weekday) => format!("BYDAY=-1{}", byday(weekday)),
+    }
+}
+
+fn byday(weekday: &Weekday) -> String {
+    weekday.to_string()[..2].to_uppercase()
+}
+
+/// First day the activity is due on from its start, as `DTSTART` has to be an occurrence
+/// of the series or calendars show it as an extra one. The start itself if there is none.
+fn first_occurrence(activity: &ActivityDetails) -> NaiveDate {
+    let start = time_utils::local_date(activity.start);
+    start
+        .iter_days()
+        .take(FIRST_OCCURRENCE_SEARCH_DAYS)
+        .find(|date| activity.is_due_on(*date))
+        .unwrap_or(start)
+}
+
+/// All-day event on `date`, shown as free time so habits never block meetings.
+fn event(uid: &str, stamp: &str, date: NaiveDate, properties: Vec<String>) -> Vec<String> {
+    let end = date.succ_opt().expect("Invalid date");
+    let mut lines = vec![
+        String::from("BEGIN:VEVENT"),
+        format!("UID:{}@yacht", uid),
+        format!("DTSTAMP:{}", stamp),
+        format!("DTSTART;VALUE=DATE:{}", ics_date(date)),
+        format!("DTEND;VALUE=DATE:{}", ics_date(end)),
+        String::from("TRANSP:TRANSPARENT"),
+    ];
+    lines.extend(properties);
+    lines.push(String::from("END:VEVENT"));
+    lines
+}
+
+// from the id, which stays the same when the activity is renamed so calendars subscribed
+// to the export keep the series and its overrides, and the random id of the data so the
+// activities of two data files never share one
+fn uid(id: u64, calendar_id: &str) -> String {
+    format!("activity-{}-{}", id, calendar_id)
+}
+
+fn ics_date(date: NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+// continuation lines start with a space, and lines are only split between characters
+fn fold(line: &str, text: &mut String) {
+    let mut length = 0;
+    for character in line.chars() {
+        if length + character.len_utf8() > MAX_LINE {
+            text.push_str("\r\n ");
+            length = 1;
+        }
+        text.push(character);
+        length += character.len_utf8();
+    }
+    text.push_str("\r\n");
+}
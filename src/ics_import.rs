@@ -0,0 +1,281 @@
+//! Import of iCalendar files, like the ones written by [`crate::ics_export`].
+//!
+//! Every recurring event becomes an activity, its `RRULE` mapped back to the matching
+//! frequency or kept as a [`FrequencyType::Rrule`]. Events with a `✓` before the summary
+//! are completions of the activity of that name, or slips with a `✗`. Anything else is
+//! listed in the report.
+
+use crate::activities_manager::{
+    ActivityDetails, ActivityKind, AllActivities, FrequencyType, IntervalAnchor, LocalDay,
+    MonthlyRule, WeekdaySet,
+};
+use crate::ics_export::{DONE, KIND, SLIPPED};
+use crate::rrule::{Freq, Rrule};
+use crate::time_utils;
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Weekday};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Activities read from a calendar, with a line per event that was left out.
+#[derive(Debug)]
+pub struct IcsImport {
+    pub activities: AllActivities,
+    pub report: Vec<String>,
+}
+
+pub fn read_calendar(path: &Path) -> io::Result<IcsImport> {
+    parse_calendar(&fs::read_to_string(path)?)
+}
+
+pub fn parse_calendar(text: &str) -> io::Result<IcsImport> {
+    let mut import = IcsImport {
+        activities: AllActivities::new(),
+        report: Vec::new(),
+    };
+    let mut completions = Vec::new();
+    for event in events(text)? {
+        let summary = event.value("SUMMARY").map(unescape).unwrap_or_default();
+        let date = event
+            .value("RECURRENCE-ID")
+            .or_else(|| event.value("DTSTART"))
+            .ok_or_else(|| invalid_data(format!("{}: event without DTSTART", summary)))
+            .and_then(parse_date)?;
+
+        if let Some(name) = summary
+            .strip_prefix(DONE)
+            .or_else(|| summary.strip_prefix(SLIPPED))
+        {
+            let times = event.value("DESCRIPTION").map(unescape).unwrap_or_default();
+            completions.push((name.trim().to_uppercase(), date, times));
+            continue;
+        }
+        let name = summary.trim().to_uppercase();
+        let Some(rule) = event.value("RRULE") else {
+            import
+                .report
+                .push(format!("{}: skipped, not a recurring event", name));
+            continue;
+        };
+        let frequency = frequency_from_rrule(rule);
+        if !frequency.is_valid() {
+            let reason = rule.parse::<Rrule>().err().unwrap_or_default();
+            import.report.push(format!(
+                "{}: skipped, unsupported RRULE {} {}",
+                name, rule, reason
+            ));
+            continue;
+        }
+        if import.activities.id_of(&name).is_some() {
+            import
+                .report
+                .push(format!("{}: skipped, listed more than once", name));
+            continue;
+        }
+        let kind = match event.value(KIND) {
+            Some(kind) if kind.eq_ignore_ascii_case("AVOID") => ActivityKind::Avoid,
+            _ => ActivityKind::Do,
+        };
+        let mut activity = ActivityDetails::new_activity(frequency).with_kind(kind);
+        activity.start = local_timestamp(date, NaiveTime::MIN);
+        import.activities.insert(name, activity);
+    }
+
+    for (name, date, times) in completions {
+        let Some(activity) = import.activities.get_mut(&name) else {
+            import.report.push(format!(
+                "{}: completion on {} skipped, no recurring event with that name",
+                name, date
+            ));
+            continue;
+        };
+        // completions count for the day of their event, whatever the local day start
+        for time in completion_times(date, &times) {
+            let time = Local
+                .from_local_datetime(&time)
+                .earliest()
+                .map(|time| time.fixed_offset())
+                .unwrap_or_else(|| Local::now().fixed_offset());
+            let day = LocalDay {
+                date,
+                offset: time.offset().local_minus_utc(),
+            };
+            activity.add_completion(time.timestamp(), day);
+        }
+    }
+    Ok(import)
+}
+
+/// Frequency with the same schedule as `rule`, a [`FrequencyType::Rrule`] if none of the
+/// others match.
+pub fn frequency_from_rrule(text: &str) -> FrequencyType {
+    let text = text.trim().to_uppercase();
+    let Ok(rule) = text.parse::<Rrule>() else {
+        return FrequencyType::Invalid;
+    };
+    let plain = rule.count.is_none()
+        && rule.until.is_none()
+        && rule.by_month.is_empty()
+        && rule.by_set_pos.is_empty();
+    let weekdays: Option<Vec<Weekday>> = rule
+        .by_day
+        .iter()
+        .map(|(nth, weekday)| nth.is_none().then_some(*weekday))
+        .collect();
+    let frequency = match (rule.freq, rule.interval) {
+        (Freq::Daily, days) if plain && rule.by_day.is_empty() && rule.by_month_day.is_empty() => {
+            match days {
+                1 => Some(FrequencyType::AllWeek),
+                days => u16::try_from(days)
+                    .ok()
+                    .map(|days| FrequencyType::Interval {
+                        days,
+                        anchor: IntervalAnchor::Start,
+                    }),
+            }
+        }
+        (Freq::Weekly, 1) if plain && !rule.by_day.is_empty() => {
+            weekdays.map(|days| match WeekdaySet::new(days) {
+                set if set == WeekdaySet::new([Weekday::Sat, Weekday::Sun]) => {
+                    FrequencyType::WeekEnds
+                }
+                set if set
+                    == WeekdaySet::new([
+                        Weekday::Mon,
+                        Weekday::Tue,
+                        Weekday::Wed,
+                        Weekday::Thu,
+                        Weekday::Fri,
+                    ]) =>
+                {
+                    FrequencyType::WorkingDays
+                }
+                set => FrequencyType::Weekdays(set),
+            })
+        }
+        (Freq::Monthly, 1) => monthly_rule(&rule).map(FrequencyType::Monthly),
+        _ => None,
+    };
+    frequency
+        .filter(FrequencyType::is_valid)
+        .unwrap_or_else(|| FrequencyType::Rrule(text.trim_start_matches("RRULE:").to_string()))
+}
+
+// the rules written by `ics_export` for each monthly rule
+fn monthly_rule(rule: &Rrule) -> Option<MonthlyRule> {
+    if rule.count.is_some() || rule.until.is_some() || !rule.by_month.is_empty() {
+        return None;
+    }
+    match (
+        rule.by_day.as_slice(),
+        rule.by_month_day.as_slice(),
+        rule.by_set_pos.as_slice(),
+    ) {
+        ([], [day], []) if (1..=28).contains(day) => Some(MonthlyRule::Day(*day as u8)),
+        ([], [28, .., last], [-1]) if rule.by_month_day == (28..=*last).collect::<Vec<_>>() => {
+            Some(MonthlyRule::Day(*last as u8))
+        }
+        ([(Some(-1), weekday)], [], []) => Some(MonthlyRule::LastWeekday(*weekday)),
+        ([(Some(nth @ 1..=5), weekday)], [], []) => Some(MonthlyRule::Weekday {
+            nth: *nth as u8,
+            weekday: *weekday,
+        }),
+        _ => None,
+    }
+}
+
+/// Properties of a `VEVENT`, without their parameters.
+#[derive(Debug, Default)]
+struct Event {
+    properties: Vec<(String, String)>,
+}
+
+impl Event {
+    fn value(&self, name: &str) -> Option<&str> {
+        self.properties
+            .iter()
+            .find(|(property, _)| property == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn events(text: &str) -> io::Result<Vec<Event>> {
+    // long lines are folded with a line break followed by a space or a tab
+    let unfolded = text
+        .replace("\r\n", "\n")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+    let mut events = Vec::new();
+    let mut event: Option<Event> = None;
+    for line in unfolded.lines().filter(|line| !line.is_empty()) {
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| invalid_data(format!("invalid line {:?}", line)))?;
+        let name = name.split(';').next().unwrap_or_default().to_uppercase();
+        match (name.as_str(), value, event.as_mut()) {
+            ("BEGIN", "VEVENT", None) => event = Some(Event::default()),
+            ("END", "VEVENT", Some(_)) => events.extend(event.take()),
+            (_, _, Some(event)) => event.properties.push((name, value.to_string())),
+            _ => {}
+        }
+    }
+    Ok(events)
+}
+
+/// Date of a `DATE` or `DATE-TIME` value, like `20240120` or `20240120T173157Z`.
+fn parse_date(value: &str) -> io::Result<NaiveDate> {
+    value
+        .get(..8)
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+        .ok_or_else(|| invalid_data(format!("invalid date {}", value)))
+}
+
+/// Times listed in the description of a completion on `date`, like `Done at 07:30:00,
+/// 18:00:00`, or with their own date like `2024-01-23 01:30:00`. Noon if there are none.
+fn completion_times(date: NaiveDate, description: &str) -> impl Iterator<Item = NaiveDateTime> {
+    let mut times: Vec<NaiveDateTime> = description
+        .split_once(" at ")
+        .map(|(_, times)| times)
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|time| {
+            let time = time.trim();
+            NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S")
+                .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M:%S").map(|t| date.and_time(t)))
+                .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M").map(|t| date.and_time(t)))
+                .ok()
+        })
+        .collect();
+    if times.is_empty() {
+        times.push(date.and_hms_opt(12, 0, 0).expect("Invalid time"));
+    }
+    times.into_iter()
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(character) = chars.next() {
+        match (character, character == '\\') {
+            (_, true) => match chars.next() {
+                Some('n' | 'N') => unescaped.push('\n'),
+                Some(escaped) => unescaped.push(escaped),
+                None => {}
+            },
+            (character, false) => unescaped.push(character),
+        }
+    }
+    unescaped
+}
+
+fn local_timestamp(date: NaiveDate, time: NaiveTime) -> i64 {
+    Local
+        .from_local_datetime(&date.and_time(time))
+        .earliest()
+        .map(|datetime| datetime.timestamp())
+        .unwrap_or_else(time_utils::current_time_timestamp)
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
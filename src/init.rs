@@ -1,27 +1,63 @@
-use crate::activities_manager::{ActivityDetails, AllActivities, FrequencyType};
+use crate::activities_manager::{ActivityDetails, AllActivities, FrequencyType, Target};
+use crate::data_location::{DataLocation, ACTIVITIES_FILE};
+use crate::file_storage::FileStorage;
+use crate::storage::{MemoryStorage, Storage, StorageKind};
 
-pub const ACTIVITIES_FILE: &str = "activities.bin";
+/// Brings data left by older versions into `storage`: `./activities.bin` for the file
+/// backend, the bincode file in the data directory for a new SQLite database.
+pub fn migrate_legacy_data(location: &DataLocation, kind: StorageKind, storage: &mut dyn Storage) {
+    match kind {
+        StorageKind::File => match location.migrate_legacy_file() {
+            Ok(Some(legacy)) => {
+                println!("Moved {} to {}", legacy.display(), location.path.display())
+            }
+            Ok(None) => {}
+            Err(e) => println!("Failed to move the old activities file: {:?}", e),
+        },
+        StorageKind::Sqlite => {
+            let bincode_file = DataLocation::default_for(ACTIVITIES_FILE).path;
+            if storage.exists() || !bincode_file.exists() {
+                return;
+            }
+            match FileStorage::new(bincode_file.clone())
+                .load()
+                .and_then(|loaded| storage.save(&loaded.value))
+            {
+                Ok(_) => println!(
+                    "Imported {} into {}",
+                    bincode_file.display(),
+                    location.path.display()
+                ),
+                Err(e) => println!("Failed to import the activities file: {:?}", e),
+            }
+        }
+    }
+}
 
-pub fn initialize_activities() {
-    // Check if the activities file exists
-    if !std::path::Path::new(ACTIVITIES_FILE).exists() {
+pub fn initialize_activities(storage: &mut dyn Storage) {
+    // Check if the activities file or one of its backups exists
+    if !storage.exists() {
         // If the file doesn't exist, initialize and save the activities
-        initialize_and_save_activities();
+        initialize_and_save_activities(storage);
     }
 
-    fn initialize_and_save_activities() {
-        // Create initial set of activities
+    fn initialize_and_save_activities(storage: &mut dyn Storage) {
+        // Create initial set of activities, saved at once below
         let mut initial_activities = AllActivities::new();
+        let scratch = &mut MemoryStorage::new();
 
         match initial_activities.add_activity(
-            String::from("Go for a 2 km run"),
-            ActivityDetails::new_activity(FrequencyType::AllWeek),
+            scratch,
+            String::from("Go for a run"),
+            ActivityDetails::new_activity(FrequencyType::AllWeek)
+                .with_target(Target::from_code("2 km")),
         ) {
             Ok(_) => println!("Done"),
             Err(e) => println!("{:?}", e),
         };
 
         match initial_activities.add_activity(
+            scratch,
             String::from("Study Marxism"),
             ActivityDetails::new_activity(FrequencyType::AllWeek),
         ) {
@@ -30,6 +66,7 @@ pub fn initialize_activities() {
         };
 
         match initial_activities.add_activity(
+            scratch,
             String::from("Complete 1 task on Exercism"),
             ActivityDetails::new_activity(FrequencyType::AllWeek),
         ) {
@@ -38,6 +75,7 @@ pub fn initialize_activities() {
         };
 
         match initial_activities.add_activity(
+            scratch,
             String::from("Play Tennis"),
             ActivityDetails::new_activity(FrequencyType::AllWeek),
         ) {
@@ -46,6 +84,7 @@ pub fn initialize_activities() {
         };
 
         match initial_activities.add_activity(
+            scratch,
             String::from("Practice a foreign language"),
             ActivityDetails::new_activity(FrequencyType::AllWeek),
         ) {
@@ -53,8 +92,8 @@ pub fn initialize_activities() {
             Err(e) => println!("{:?}", e),
         };
 
-        initial_activities
-            .save_to_file()
+        storage
+            .save(&initial_activities)
             .expect("Failed to save initial activities");
     }
 }
@@ -0,0 +1,589 @@
+//! Human-readable JSON and TOML export and import of every activity and its history.
+
+use crate::activities_manager::{
+    ActivityDetails, ActivityKind, AllActivities, FrequencyType, LocalDay, Pause, Target,
+};
+use crate::time_utils;
+use chrono::{DateTime, FixedOffset, Local, NaiveDate};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Version of the [`Document`] layout. Version 2 added the day of completions and version 3
+/// the one of progress, older documents are still read.
+pub const DOCUMENT_VERSION: u32 = 3;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Json,
+    Toml,
+    /// Export only, see [`crate::csv_export`].
+    Csv,
+    /// Not a [`Document`], see [`crate::ics_export`] and [`crate::ics_import`].
+    Ics,
+}
+
+impl Format {
+    /// Format matching the file extension, if it is a known one.
+    pub fn from_path(path: &Path) -> Option<Format> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "json" => Ok(Format::Json),
+            "toml" => Ok(Format::Toml),
+            "csv" => Ok(Format::Csv),
+            "ics" => Ok(Format::Ics),
+            _ => Err(format!(
+                "Unknown format: {}. Please use json, toml, csv or ics.",
+                value
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Adds new activities, and the completions, progress, skips and pauses the existing ones
+    /// don't have. Their settings and what they already have are kept.
+    #[default]
+    Merge,
+    /// Makes the activities exactly the ones in the document. Removed activities it doesn't
+    /// list are kept.
+    Replace,
+}
+
+impl FromStr for ImportMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "merge" => Ok(ImportMode::Merge),
+            "replace" => Ok(ImportMode::Replace),
+            _ => Err(format!(
+                "Unknown import mode: {}. Please use merge or replace.",
+                value
+            )),
+        }
+    }
+}
+
+/// Exported activities.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    pub version: u32,
+    /// Pauses of the whole tracker, like `2024-01-20..2024-01-27`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pauses: Vec<String>,
+    pub activities: Vec<ActivityRecord>,
+}
+
+/// One activity, with times in RFC 3339 and the frequency as its TUI code.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ActivityRecord {
+    pub name: String,
+    pub start: DateTime<FixedOffset>,
+    pub frequency: String,
+    /// `do`, or `avoid` for activities whose completions are slips.
+    #[serde(default)]
+    pub kind: ActivityKind,
+    #[serde(default, deserialize_with = "completion_records")]
+    pub completions: Vec<CompletionRecord>,
+    /// Target of quantitative activities, like `2 km`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Days the activity is paused, like `2024-01-20..2024-01-27`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pauses: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skips: Vec<SkipRecord>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub progress: Vec<ProgressRecord>,
+    /// Day the activity was removed on, kept for its history before that day.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub removed: Option<NaiveDate>,
+}
+
+/// Time of a completion, in the offset it was logged at, and the day it counts for.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompletionRecord {
+    pub time: DateTime<FixedOffset>,
+    pub day: NaiveDate,
+}
+
+/// Completion as listed by either version of the document.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AnyCompletion {
+    Record(CompletionRecord),
+    /// Version 1 only listed the time, which counts for its day under the local day start.
+    Time(DateTime<FixedOffset>),
+}
+
+fn completion_records<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<CompletionRecord>, D::Error> {
+    let completions = Vec::<AnyCompletion>::deserialize(deserializer)?;
+    Ok(completions
+        .into_iter()
+        .map(|completion| match completion {
+            AnyCompletion::Record(record) => record,
+            AnyCompletion::Time(time) => CompletionRecord {
+                day: time_utils::day_of(&time, time_utils::day_start()),
+                time,
+            },
+        })
+        .collect())
+}
+
+/// Day an activity was skipped, and why.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SkipRecord {
+    pub date: NaiveDate,
+    pub reason: String,
+}
+
+/// Amount logged towards the target of a quantitative activity, at a time in the offset it
+/// was logged at.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProgressRecord {
+    pub time: DateTime<FixedOffset>,
+    pub amount: f64,
+    /// Day the amount counts for, missing from documents before version 3 where it is the
+    /// local day of `time`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub day: Option<NaiveDate>,
+}
+
+impl Document {
+    pub fn from_activities(all: &AllActivities) -> Document {
+        let mut activities: Vec<ActivityRecord> = all
+            .activities
+            .values()
+            .map(|activity| ActivityRecord {
+                name: activity.name.clone(),
+                start: to_datetime(activity.start),
+                frequency: activity.frequency.code(),
+                kind: activity.kind,
+                completions: activity
+                    .completion_timestamps
+                    .iter()
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .map(|timestamp| {
+                        let day = activity.completion_day(*timestamp);
+                        CompletionRecord {
+                            time: day.time_of(*timestamp),
+                            day: day.date,
+                        }
+                    })
+                    .collect(),
+                target: activity.target.as_ref().map(Target::code),
+                pauses: activity.pauses.iter().map(Pause::code).collect(),
+                skips: activity
+                    .skips
+                    .iter()
+                    .map(|(date, reason)| SkipRecord {
+                        date: *date,
+                        reason: reason.clone(),
+                    })
+                    .collect(),
+                progress: activity
+                    .progress
+                    .iter()
+                    .map(|(timestamp, amount)| {
+                        let day = activity.progress_day(*timestamp);
+                        ProgressRecord {
+                            time: day.time_of(*timestamp),
+                            amount: *amount,
+                            day: Some(day.date),
+                        }
+                    })
+                    .collect(),
+                removed: activity.removed,
+            })
+            .collect();
+        // removed activities before the current one taking their name, the oldest first
+        activities.sort_by(|a, b| {
+            (&a.name, a.removed.is_none(), a.removed).cmp(&(
+                &b.name,
+                b.removed.is_none(),
+                b.removed,
+            ))
+        });
+        Document {
+            version: DOCUMENT_VERSION,
+            pauses: all.pauses.iter().map(Pause::code).collect(),
+            activities,
+        }
+    }
+
+    pub fn render(&self, format: Format) -> io::Result<String> {
+        match format {
+            Format::Json => serde_json::to_string_pretty(self).map_err(io::Error::other),
+            Format::Toml => toml::to_string_pretty(self).map_err(io::Error::other),
+            Format::Csv | Format::Ics => Err(export_only()),
+        }
+    }
+
+    pub fn parse(text: &str, format: Format) -> io::Result<Document> {
+        let document: Document = match format {
+            Format::Json => serde_json::from_str(text).map_err(invalid_data)?,
+            Format::Toml => toml::from_str(text).map_err(invalid_data)?,
+            Format::Csv | Format::Ics => return Err(export_only()),
+        };
+        match document.version {
+            1..=DOCUMENT_VERSION => Ok(document),
+            version => Err(invalid_data(format!(
+                "document version {} is not supported",
+                version
+            ))),
+        }
+    }
+
+    /// Converts the records, rejecting unknown frequencies and duplicate names.
+    pub fn to_activities(&self) -> io::Result<AllActivities> {
+        let mut all = AllActivities::new();
+        all.pauses =
+            parse_pauses(&self.pauses).map_err(|e| invalid_data(format!("tracker: {}", e)))?;
+        for record in &self.activities {
+            let name = record.name.trim().to_uppercase();
+            let frequency = FrequencyType::from_code(&record.frequency);
+            if !frequency.is_valid() {
+                return Err(invalid_data(format!(
+                    "{}: invalid frequency {:?}",
+                    name, record.frequency
+                )));
+            }
+            let target =
+                match &record.target {
+                    Some(code) => Some(Target::from_code(code).ok_or_else(|| {
+                        invalid_data(format!("{}: invalid target {:?}", name, code))
+                    })?),
+                    None => None,
+                };
+            let mut activity = ActivityDetails::new_activity(frequency)
+                .with_target(target)
+                .with_kind(record.kind);
+            activity
+                .check()
+                .map_err(|e| invalid_data(format!("{}: {}", name, e.to_lowercase())))?;
+            activity.start = record.start.timestamp();
+            activity.pauses = parse_pauses(&record.pauses)
+                .map_err(|e| invalid_data(format!("{}: {}", name, e)))?;
+            for completion in &record.completions {
+                let day = LocalDay {
+                    date: completion.day,
+                    offset: completion.time.offset().local_minus_utc(),
+                };
+                activity.add_completion(completion.time.timestamp(), day);
+            }
+            for skip in &record.skips {
+                let reason = skip.reason.trim();
+                if reason.is_empty() {
+                    return Err(invalid_data(format!(
+                        "{}: skip on {} without a reason",
+                        name, skip.date
+                    )));
+                }
+                activity.skips.insert(skip.date, reason.to_string());
+            }
+            for progress in &record.progress {
+                if !(progress.amount.is_finite() && progress.amount > 0.0) {
+                    return Err(invalid_data(format!(
+                        "{}: invalid amount {}",
+                        name, progress.amount
+                    )));
+                }
+                let day = LocalDay {
+                    date: progress.day.unwrap_or_else(|| {
+                        time_utils::day_of(&progress.time, time_utils::day_start())
+                    }),
+                    offset: progress.time.offset().local_minus_utc(),
+                };
+                activity.add_progress(progress.time.timestamp(), progress.amount, day);
+            }
+            // removed activities gave their name up
+            activity.removed = record.removed;
+            if !activity.is_removed() && all.id_of(&name).is_some() {
+                return Err(invalid_data(format!("{}: listed more than once", name)));
+            }
+            all.insert(name, activity);
+        }
+        Ok(all)
+    }
+}
+
+/// Reads an exported file, guessing the format from the extension unless given.
+pub fn read_document(path: &Path, format: Option<Format>) -> io::Result<Document> {
+    let format = format
+        .or_else(|| Format::from_path(path))
+        .unwrap_or_default();
+    Document::parse(&fs::read_to_string(path)?, format)
+}
+
+/// Result of importing a document, with a line per change for previews.
+#[derive(Debug)]
+pub struct ImportPlan {
+    pub activities: AllActivities,
+    pub changes: Vec<String>,
+}
+
+/// Works out what importing `imported` into `current` would result in.
+pub fn plan_import(
+    current: &AllActivities,
+    imported: &AllActivities,
+    mode: ImportMode,
+) -> ImportPlan {
+    // activities already tracked keep their id, and new ones never reuse a removed one
+    let mut activities = match mode {
+        ImportMode::Merge => current.clone(),
+        ImportMode::Replace => AllActivities {
+            activities: current
+                .activities
+                .iter()
+                .filter(|(_, activity)| activity.is_removed())
+                .map(|(id, activity)| (*id, activity.clone()))
+                .collect(),
+            last_id: current.last_id,
+            pauses: imported.pauses.clone(),
+        },
+    };
+    let mut changes = Vec::new();
+    if mode == ImportMode::Merge {
+        activities.pauses = merge_pauses(&current.pauses, &imported.pauses);
+    }
+    if activities.pauses != current.pauses {
+        changes.push(format!(
+            "~ tracker: pauses {} -> {}",
+            pause_codes(&current.pauses),
+            pause_codes(&activities.pauses)
+        ));
+    }
+
+    // removed activities are only restored by a replace, a merge is about the current ones
+    if mode == ImportMode::Replace {
+        let mut removed: Vec<&ActivityDetails> = imported
+            .activities
+            .values()
+            .filter(|activity| activity.is_removed())
+            .collect();
+        removed.sort_by(|a, b| (&a.name, a.removed).cmp(&(&b.name, b.removed)));
+        for new in removed {
+            let kept = activities.activities.iter_mut().find(|(_, old)| {
+                old.name == new.name && old.removed == new.removed && old.start == new.start
+            });
+            match kept {
+                Some((_, old)) => *old = new.clone(),
+                None => {
+                    changes.push(format!(
+                        "+ {}: removed on {} with {} completions",
+                        new.name,
+                        new.removed.unwrap_or_default(),
+                        new.completion_timestamps.len()
+                    ));
+                    activities.insert(new.name.clone(), new.clone());
+                }
+            }
+        }
+    }
+
+    let mut new_activities: Vec<&ActivityDetails> =
+        imported.current().map(|(_, activity)| activity).collect();
+    new_activities.sort_by(|a, b| a.name.cmp(&b.name));
+    for new in new_activities {
+        let name = &new.name;
+        let Some((id, old)) = current.id_of(name).zip(current.get(name)) else {
+            changes.push(format!(
+                "+ {}: new {} activity with {} completions",
+                name,
+                new.frequency.code(),
+                new.completion_timestamps.len()
+            ));
+            activities.insert(name.clone(), new.clone());
+            continue;
+        };
+
+        let merged = match mode {
+            ImportMode::Merge => {
+                let mut merged = old.clone();
+                for timestamp in &new.completion_timestamps {
+                    if !merged.completion_timestamps.contains(timestamp) {
+                        merged.add_completion(*timestamp, new.completion_day(*timestamp));
+                    }
+                }
+                for (timestamp, amount) in &new.progress {
+                    if !merged.progress.contains_key(timestamp) {
+                        merged.add_progress(*timestamp, *amount, new.progress_day(*timestamp));
+                    }
+                }
+                merged.pauses = merge_pauses(&old.pauses, &new.pauses);
+                for (date, reason) in &new.skips {
+                    merged.skips.entry(*date).or_insert_with(|| reason.clone());
+                }
+                merged
+            }
+            ImportMode::Replace => new.clone(),
+        };
+        let mut details = Vec::new();
+        if old.frequency != merged.frequency {
+            details.push(format!(
+                "frequency {} -> {}",
+                old.frequency.code(),
+                merged.frequency.code()
+            ));
+        }
+        if old.kind != merged.kind {
+            details.push(format!(
+                "kind {} -> {}",
+                old.kind.code(),
+                merged.kind.code()
+            ));
+        }
+        if old.target != merged.target {
+            let code = |target: &Option<Target>| {
+                target.as_ref().map_or(String::from("none"), Target::code)
+            };
+            details.push(format!(
+                "target {} -> {}",
+                code(&old.target),
+                code(&merged.target)
+            ));
+        }
+        if old.pauses != merged.pauses {
+            details.push(format!(
+                "pauses {} -> {}",
+                pause_codes(&old.pauses),
+                pause_codes(&merged.pauses)
+            ));
+        }
+        if old.start != merged.start {
+            details.push(format!(
+                "start {} -> {}",
+                to_datetime(old.start),
+                to_datetime(merged.start)
+            ));
+        }
+        let added = count_missing(&merged.completion_timestamps, &old.completion_timestamps);
+        let removed = count_missing(&old.completion_timestamps, &merged.completion_timestamps);
+        if added > 0 {
+            details.push(format!("{} completions added", added));
+        }
+        if removed > 0 {
+            details.push(format!("{} completions removed", removed));
+        }
+        let logged = |from: &ActivityDetails, to: &ActivityDetails| {
+            from.progress
+                .iter()
+                .filter(|(timestamp, amount)| to.progress.get(timestamp) != Some(amount))
+                .count()
+        };
+        let (added, removed) = (logged(&merged, old), logged(old, &merged));
+        if added > 0 {
+            details.push(format!("{} progress entries added", added));
+        }
+        if removed > 0 {
+            details.push(format!("{} progress entries removed", removed));
+        }
+        let skipped = |from: &ActivityDetails, to: &ActivityDetails| {
+            from.skips
+                .iter()
+                .filter(|(date, reason)| to.skips.get(date) != Some(reason))
+                .count()
+        };
+        let (added, removed) = (skipped(&merged, old), skipped(old, &merged));
+        if added > 0 {
+            details.push(format!("{} skips added", added));
+        }
+        if removed > 0 {
+            details.push(format!("{} skips removed", removed));
+        }
+        if !details.is_empty() {
+            changes.push(format!("~ {}: {}", name, details.join(", ")));
+        }
+        activities.activities.insert(id, merged);
+    }
+
+    if mode == ImportMode::Replace {
+        let mut removed: Vec<&ActivityDetails> = current
+            .current()
+            .map(|(_, activity)| activity)
+            .filter(|activity| imported.get(&activity.name).is_none())
+            .collect();
+        removed.sort_by(|a, b| a.name.cmp(&b.name));
+        for activity in removed {
+            changes.push(format!(
+                "- {}: removed with {} completions",
+                activity.name,
+                activity.completion_timestamps.len()
+            ));
+        }
+    }
+
+    ImportPlan {
+        activities,
+        changes,
+    }
+}
+
+/// Reads ranges like `2024-01-20..2024-01-27`, both ends being required in documents.
+fn parse_pauses(codes: &[String]) -> Result<Vec<Pause>, String> {
+    codes
+        .iter()
+        .map(|code| {
+            code.contains("..")
+                .then(|| Pause::from_code(code, Local::now().date_naive()))
+                .flatten()
+                .ok_or_else(|| format!("invalid pause {:?}", code))
+        })
+        .collect()
+}
+
+/// `old` followed by the pauses of `new` it doesn't have.
+fn merge_pauses(old: &[Pause], new: &[Pause]) -> Vec<Pause> {
+    let mut merged = old.to_vec();
+    merged.extend(new.iter().filter(|pause| !old.contains(pause)));
+    merged
+}
+
+fn pause_codes(pauses: &[Pause]) -> String {
+    match pauses.is_empty() {
+        true => String::from("none"),
+        false => pauses
+            .iter()
+            .map(Pause::code)
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+fn count_missing(from: &HashSet<i64>, to: &HashSet<i64>) -> usize {
+    from.difference(to).count()
+}
+
+fn to_datetime(timestamp: i64) -> DateTime<FixedOffset> {
+    DateTime::from_timestamp(timestamp, 0)
+        .expect("Invalid timestamp")
+        .with_timezone(&Local)
+        .fixed_offset()
+}
+
+fn export_only() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "CSV and iCalendar only hold the schedule, use json or toml for documents",
+    )
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
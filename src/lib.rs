@@ -15,8 +15,55 @@ pub mod handler;
 
 pub mod init;
 
+/// Location of the data file.
+pub mod data_location;
+
+/// Command line arguments.
+pub mod cli;
+
+/// JSON and TOML export and import.
+pub mod interchange;
+
+/// CSV export of the completion history.
+pub mod csv_export;
+
+/// iCalendar export of the schedules and completions.
+pub mod ics_export;
+
+/// iCalendar import of activities exported with `ics_export`, or made elsewhere.
+pub mod ics_import;
+
+/// RFC 5545 recurrence rules.
+pub mod rrule;
+
+/// Import of Loop Habit Tracker exports.
+pub mod loop_import;
+
+/// Command line commands.
+pub mod commands;
+
 pub mod activities_manager;
 
+/// Undo and redo of changes.
+pub mod history;
+
+/// Versioned on-disk format.
+pub mod file_format;
+
+/// Atomic writes and backups.
+pub mod safe_file;
+
+/// Storage backends.
+pub mod storage;
+
+/// Bincode file storage.
+pub mod file_storage;
+
+/// SQLite storage.
+#[cfg(feature = "sqlite")]
+pub mod sqlite_storage;
+
+#[cfg(test)]
 mod tests;
 
 pub mod time_utils;
@@ -0,0 +1,307 @@
+//! Import of Loop Habit Tracker CSV exports.
+//!
+//! The export is a zip with a `Habits.csv` listing every habit and a `NNN Name/Checkmarks.csv`
+//! per habit, `NNN` being the habit position. Habits yacht cannot represent are left out and
+//! listed in the report. Numerical habits become quantitative activities, their amounts
+//! being kept in thousandths by Loop.
+
+use crate::activities_manager::{
+    self, ActivityDetails, AllActivities, FrequencyType, IntervalAnchor, QuotaPeriod, Target,
+};
+use crate::time_utils;
+use chrono::{Local, NaiveDate, NaiveTime, TimeZone};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Checkmark value of a day the habit was checked by hand.
+const YES_MANUAL: i64 = 2;
+
+/// Checkmark value of a skipped day.
+const SKIP: i64 = 3;
+
+/// Reason of the skips imported, which Loop doesn't ask for.
+const SKIP_REASON: &str = "Skipped in Loop";
+
+/// Activities read from an export, with a line per habit that was left out.
+#[derive(Debug)]
+pub struct LoopImport {
+    pub activities: AllActivities,
+    pub report: Vec<String>,
+}
+
+/// Reads an export zip, or a directory it was extracted to.
+pub fn read_export(path: &Path) -> io::Result<LoopImport> {
+    let files = match path.is_dir() {
+        true => read_dir_files(path)?,
+        false => read_zip_files(fs::File::open(path)?)?,
+    };
+    parse_export(&files)
+}
+
+/// Maps the CSV files of an export, keyed by their `/` separated path, to activities.
+pub fn parse_export(files: &BTreeMap<String, String>) -> io::Result<LoopImport> {
+    let habits = files
+        .iter()
+        .find(|(path, _)| file_name(path) == "Habits.csv")
+        .map(|(_, text)| text)
+        .ok_or_else(|| invalid_data("Habits.csv not found in the export"))?;
+
+    let mut import = LoopImport {
+        activities: AllActivities::new(),
+        report: Vec::new(),
+    };
+    for habit in parse_habits(habits)? {
+        let name = habit.name.trim().to_uppercase();
+        let settings = habit
+            .frequency()
+            .and_then(|frequency| Ok((frequency, habit.target()?)));
+        let (frequency, target) = match settings {
+            Ok(settings) => settings,
+            Err(reason) => {
+                import.report.push(format!("{}: skipped, {}", name, reason));
+                continue;
+            }
+        };
+        if import.activities.id_of(&name).is_some() {
+            import
+                .report
+                .push(format!("{}: skipped, listed more than once", name));
+            continue;
+        }
+
+        let prefix = format!("{} ", habit.position);
+        let checkmarks = files.iter().find(|(path, _)| {
+            file_name(path) == "Checkmarks.csv"
+                && parent_name(path).is_some_and(|dir| dir.starts_with(&prefix))
+        });
+        let mut activity = ActivityDetails::new_activity(frequency).with_target(target);
+        match checkmarks {
+            Some((_, text)) => {
+                let checkmarks = parse_checkmarks(text)?;
+                if let Some(first) = checkmarks.iter().map(|(date, _)| *date).min() {
+                    activity.start = local_timestamp(first, NaiveTime::MIN);
+                }
+                for (date, value) in checkmarks {
+                    let timestamp = local_timestamp(date, noon());
+                    match &activity.target {
+                        Some(target) if value > 0 => {
+                            let amount = value as f64 / 1000.0;
+                            activity.progress.insert(timestamp, amount);
+                            if amount >= target.amount - 1e-9 {
+                                activity.completion_timestamps.insert(timestamp);
+                            }
+                        }
+                        None if value == YES_MANUAL => {
+                            activity.completion_timestamps.insert(timestamp);
+                        }
+                        None if value == SKIP => {
+                            activity.skips.insert(date, String::from(SKIP_REASON));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            None => import.report.push(format!(
+                "{}: no Checkmarks.csv, imported without history",
+                name
+            )),
+        }
+        import.activities.insert(name, activity);
+    }
+    Ok(import)
+}
+
+/// A row of `Habits.csv`, from either the old or the current column layout.
+#[derive(Debug)]
+struct Habit {
+    position: String,
+    name: String,
+    numerical: bool,
+    archived: bool,
+    numerator: u32,
+    denominator: u32,
+    unit: String,
+    at_most: bool,
+    target_value: String,
+}
+
+impl Habit {
+    fn frequency(&self) -> Result<FrequencyType, String> {
+        if self.archived {
+            return Err(String::from("archived"));
+        }
+        // Loop keeps weekly and monthly quotas as n/7 and n/30, and every d days as 1/d
+        let frequency = match (self.numerator, self.denominator) {
+            (n, d) if n > 0 && n == d => FrequencyType::AllWeek,
+            (n, 7) => FrequencyType::Quota {
+                times: u8::try_from(n).unwrap_or(0),
+                period: QuotaPeriod::Week,
+            },
+            (n, 30) => FrequencyType::Quota {
+                times: u8::try_from(n).unwrap_or(0),
+                period: QuotaPeriod::Month,
+            },
+            // a check counts for the next d days
+            (1, d) => FrequencyType::Interval {
+                days: u16::try_from(d).unwrap_or(0),
+                anchor: IntervalAnchor::LastCompletion,
+            },
+            _ => FrequencyType::Invalid,
+        };
+        match frequency.is_valid() {
+            true => Ok(frequency),
+            false => Err(format!(
+                "{} times every {} days has no matching frequency",
+                self.numerator, self.denominator
+            )),
+        }
+    }
+
+    /// Daily target of numerical habits, whose frequency is the period of the target.
+    fn target(&self) -> Result<Option<Target>, String> {
+        if !self.numerical {
+            return Ok(None);
+        }
+        if self.at_most {
+            return Err(String::from(
+                "numerical habits with an at most target are not supported",
+            ));
+        }
+        if self.denominator != 1 {
+            return Err(format!(
+                "targets over {} days are not supported",
+                self.denominator
+            ));
+        }
+        match activities_manager::parse_amount(&self.target_value) {
+            Some(amount) => Ok(Some(Target {
+                amount,
+                unit: self.unit.to_lowercase(),
+            })),
+            None => Err(format!("invalid target {:?}", self.target_value)),
+        }
+    }
+}
+
+fn parse_habits(text: &str) -> io::Result<Vec<Habit>> {
+    let mut reader = csv::Reader::from_reader(text.as_bytes());
+    let headers = reader.headers().map_err(invalid_data)?.clone();
+    let column = |names: &[&str]| headers.iter().position(|header| names.contains(&header));
+    let required = |names: &[&str]| {
+        column(names).ok_or_else(|| invalid_data(format!("Habits.csv has no {} column", names[0])))
+    };
+    let position = required(&["Position"])?;
+    let name = required(&["Name"])?;
+    let numerator = required(&["FrequencyNumerator", "NumRepetitions"])?;
+    let denominator = required(&["FrequencyDenominator", "Interval"])?;
+    let kind = column(&["Type"]);
+    let archived = column(&["Archived?"]);
+    let unit = column(&["Unit"]);
+    let target_type = column(&["Target Type"]);
+    let target_value = column(&["Target Value"]);
+
+    let mut habits = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(invalid_data)?;
+        let field = |index: usize| record.get(index).unwrap_or_default().trim();
+        let number = |index: usize| {
+            field(index)
+                .parse()
+                .map_err(|_| invalid_data(format!("{}: invalid frequency", field(name))))
+        };
+        habits.push(Habit {
+            position: field(position).to_string(),
+            name: field(name).to_string(),
+            numerical: kind.is_some_and(|index| field(index) == "NUMERICAL"),
+            archived: archived.is_some_and(|index| field(index) == "true"),
+            numerator: number(numerator)?,
+            denominator: number(denominator)?,
+            unit: unit.map(field).unwrap_or_default().to_string(),
+            at_most: target_type.is_some_and(|index| field(index) == "AT_MOST"),
+            target_value: target_value.map(field).unwrap_or_default().to_string(),
+        });
+    }
+    Ok(habits)
+}
+
+/// Days and values of a habit's `Checkmarks.csv`, which has no header.
+fn parse_checkmarks(text: &str) -> io::Result<Vec<(NaiveDate, i64)>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(text.as_bytes());
+    let mut checkmarks = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(invalid_data)?;
+        let (Some(date), Some(value)) = (record.get(0), record.get(1)) else {
+            return Err(invalid_data("Checkmarks.csv rows need a date and a value"));
+        };
+        let date = date.trim().parse().map_err(invalid_data)?;
+        let value = value.trim().parse().map_err(invalid_data)?;
+        checkmarks.push((date, value));
+    }
+    Ok(checkmarks)
+}
+
+fn read_zip_files<R: Read + io::Seek>(reader: R) -> io::Result<BTreeMap<String, String>> {
+    let mut archive = zip::ZipArchive::new(reader).map_err(invalid_data)?;
+    let mut files = BTreeMap::new();
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index).map_err(invalid_data)?;
+        let name = file.name().map_err(invalid_data)?.to_string();
+        if !file.is_file() || !name.ends_with(".csv") {
+            continue;
+        }
+        let mut text = String::new();
+        file.read_to_string(&mut text)?;
+        files.insert(name, text);
+    }
+    Ok(files)
+}
+
+fn read_dir_files(root: &Path) -> io::Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|extension| extension == "csv") {
+                let relative = path.strip_prefix(root).unwrap_or(&path);
+                let key = relative
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                files.insert(key, fs::read_to_string(&path)?);
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+fn parent_name(path: &str) -> Option<&str> {
+    path.rsplit('/').nth(1)
+}
+
+fn noon() -> NaiveTime {
+    NaiveTime::from_hms_opt(12, 0, 0).expect("Invalid time")
+}
+
+fn local_timestamp(date: NaiveDate, time: NaiveTime) -> i64 {
+    Local
+        .from_local_datetime(&date.and_time(time))
+        .earliest()
+        .map(|datetime| datetime.timestamp())
+        .unwrap_or_else(time_utils::current_time_timestamp)
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
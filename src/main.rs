@@ -1,23 +1,56 @@
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
-use std::io;
+use std::{env, io, process};
 use yacht::app::{App, AppResult};
+use yacht::cli::{Cli, Command, USAGE};
+use yacht::commands;
+use yacht::data_location::DataLocation;
 use yacht::event::{Event, EventHandler};
 use yacht::handler::handle_key_events;
-use yacht::init::initialize_activities;
+use yacht::history::{self, History};
+use yacht::init::{initialize_activities, migrate_legacy_data};
+use yacht::storage::StorageKind;
+use yacht::time_utils;
 use yacht::tui::Tui;
 
 fn main() -> AppResult<()> {
+    let cli = match Cli::parse(env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+    if cli.help {
+        print!("{}", USAGE);
+        return Ok(());
+    }
+    time_utils::set_day_start(time_utils::resolve_day_start(cli.day_start)?);
+
+    if let Command::ImportBin(source) = cli.command {
+        let location = DataLocation::resolve(cli.data_file, StorageKind::Sqlite.file_name());
+        return commands::import_bin(&location.path, source);
+    }
+
     //init
-    initialize_activities();
+    let kind = StorageKind::resolve(cli.storage)?;
+    let location = DataLocation::resolve(cli.data_file, kind.file_name());
+    let mut storage = kind.open(&location.path)?;
+    migrate_legacy_data(&location, kind, storage.as_mut());
+    if cli.command != Command::Tui {
+        return commands::run(cli.command, storage.as_mut());
+    }
+    initialize_activities(storage.as_mut());
 
     // Create an application.
-    let mut app = App::new();
+    let history = History::load(history::history_path(&location.path));
+    let mut app = App::new(storage).with_history(history);
 
     // Initialize the terminal user interface.
     let backend = CrosstermBackend::new(io::stderr());
     let terminal = Terminal::new(backend)?;
     let events = EventHandler::new(30000); //30secs
+    events.watch(location.path.clone(), 1000);
     let mut tui = Tui::new(terminal, events);
     tui.init()?;
 
@@ -28,6 +61,7 @@ fn main() -> AppResult<()> {
         // Handle events.
         match tui.events.next()? {
             Event::Tick => app.tick(),
+            Event::DataChanged => app.reload(),
             Event::Key(key_event) => handle_key_events(key_event, &mut app)?,
             Event::Mouse(_) => {}
             Event::Resize(_, _) => {}
@@ -0,0 +1,299 @@
+//! RFC 5545 recurrence rules evaluated on calendar dates.
+//!
+//! Rules are matched day by day, with the activity start as `DTSTART`. Times of day are
+//! ignored, so `UNTIL` only counts its date, and parts finer than a day like `BYHOUR` are
+//! rejected along with the rarely used `BYWEEKNO` and `BYYEARDAY`.
+
+use chrono::{Datelike, Duration, Months, NaiveDate, Weekday};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Freq {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// A parsed `RRULE` value, like `FREQ=MONTHLY;BYDAY=-1SU`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rrule {
+    pub freq: Freq,
+    pub interval: u32,
+    /// Weekdays, with the position in the month or year for monthly and yearly rules.
+    pub by_day: Vec<(Option<i8>, Weekday)>,
+    /// Days of the month, negative ones counting from its end.
+    pub by_month_day: Vec<i8>,
+    pub by_month: Vec<u32>,
+    pub by_set_pos: Vec<i32>,
+    pub count: Option<u32>,
+    pub until: Option<NaiveDate>,
+    pub week_start: Weekday,
+}
+
+impl FromStr for Rrule {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        let text = text.strip_prefix("RRULE:").unwrap_or(text);
+        let mut freq = None;
+        let mut rule = Rrule {
+            freq: Freq::Daily,
+            interval: 1,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+            by_set_pos: Vec::new(),
+            count: None,
+            until: None,
+            week_start: Weekday::Mon,
+        };
+        for part in text.split(';').filter(|part| !part.is_empty()) {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("{} has no value", part))?;
+            let invalid = || format!("invalid {} value {}", name, value);
+            match name.to_uppercase().as_str() {
+                "FREQ" => {
+                    freq = Some(match value.to_uppercase().as_str() {
+                        "DAILY" => Freq::Daily,
+                        "WEEKLY" => Freq::Weekly,
+                        "MONTHLY" => Freq::Monthly,
+                        "YEARLY" => Freq::Yearly,
+                        _ => return Err(format!("unsupported FREQ {}", value)),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval = value.parse().map_err(|_| invalid())?;
+                    if rule.interval == 0 {
+                        return Err(invalid());
+                    }
+                }
+                "COUNT" => rule.count = Some(value.parse().map_err(|_| invalid())?),
+                "UNTIL" => {
+                    let date = value.get(..8).ok_or_else(invalid)?;
+                    rule.until =
+                        Some(NaiveDate::parse_from_str(date, "%Y%m%d").map_err(|_| invalid())?);
+                }
+                "BYDAY" => rule.by_day = split(value, |day| parse_by_day(day).ok_or_else(invalid))?,
+                "BYMONTHDAY" => {
+                    rule.by_month_day = split(value, |day| match day.parse() {
+                        Ok(day @ (-31..=-1 | 1..=31)) => Ok(day),
+                        _ => Err(invalid()),
+                    })?
+                }
+                "BYMONTH" => {
+                    rule.by_month = split(value, |month| match month.parse() {
+                        Ok(month @ 1..=12) => Ok(month),
+                        _ => Err(invalid()),
+                    })?
+                }
+                "BYSETPOS" => {
+                    rule.by_set_pos = split(value, |pos| match pos.parse() {
+                        Ok(pos @ (-366..=-1 | 1..=366)) => Ok(pos),
+                        _ => Err(invalid()),
+                    })?
+                }
+                "WKST" => rule.week_start = parse_weekday(value).ok_or_else(invalid)?,
+                _ => return Err(format!("{} is not supported", name)),
+            }
+        }
+        rule.freq = freq.ok_or("FREQ is required")?;
+        if rule.count.is_some() && rule.until.is_some() {
+            return Err(String::from("COUNT and UNTIL can't be used together"));
+        }
+        let positioned = rule.by_day.iter().any(|(nth, _)| nth.is_some());
+        if positioned && matches!(rule.freq, Freq::Daily | Freq::Weekly) {
+            return Err(String::from(
+                "BYDAY positions only apply to MONTHLY and YEARLY rules",
+            ));
+        }
+        if !rule.by_month_day.is_empty() && rule.freq == Freq::Weekly {
+            return Err(String::from("BYMONTHDAY doesn't apply to WEEKLY rules"));
+        }
+        Ok(rule)
+    }
+}
+
+impl Rrule {
+    /// True if the rule starting on `start` has an occurrence on `date`.
+    pub fn occurs_on(&self, start: NaiveDate, date: NaiveDate) -> bool {
+        if date < start || self.until.is_some_and(|until| date > until) {
+            return false;
+        }
+        match self.count {
+            // the occurrences before `date` have to be counted
+            Some(count) => self
+                .occurrences(start, date)
+                .take(count as usize)
+                .any(|occurrence| occurrence == date),
+            None => {
+                let index = self.period_index(start, date);
+                index % i64::from(self.interval) == 0
+                    && self.period_occurrences(start, date).contains(&date)
+            }
+        }
+    }
+
+    /// Occurrences from `start` until `last`, in order, ignoring `COUNT`.
+    pub fn occurrences(
+        &self,
+        start: NaiveDate,
+        last: NaiveDate,
+    ) -> impl Iterator<Item = NaiveDate> + '_ {
+        let last = self.until.map_or(last, |until| until.min(last));
+        (0..)
+            .map(move |n| self.period_start(start, n * i64::from(self.interval)))
+            .take_while(move |period| *period <= last)
+            .flat_map(move |period| self.period_occurrences(start, period))
+            .filter(move |date| *date >= start && *date <= last)
+    }
+
+    /// Number of periods of the rule between the ones of `start` and `date`.
+    fn period_index(&self, start: NaiveDate, date: NaiveDate) -> i64 {
+        let months = |date: NaiveDate| i64::from(date.year()) * 12 + i64::from(date.month0());
+        match self.freq {
+            Freq::Daily => (date - start).num_days(),
+            Freq::Weekly => (self.week_of(date) - self.week_of(start)).num_weeks(),
+            Freq::Monthly => months(date) - months(start),
+            Freq::Yearly => i64::from(date.year() - start.year()),
+        }
+    }
+
+    /// First day of the `n`-th period after the one of `start`.
+    fn period_start(&self, start: NaiveDate, n: i64) -> NaiveDate {
+        let months = |n: i64| Months::new(u32::try_from(n).unwrap_or(u32::MAX));
+        let first_of_month = start.with_day(1).expect("Invalid date");
+        match self.freq {
+            Freq::Daily => start + Duration::days(n),
+            Freq::Weekly => self.week_of(start) + Duration::weeks(n),
+            Freq::Monthly => first_of_month + months(n),
+            Freq::Yearly => first_of_month.with_month(1).expect("Invalid date") + months(n * 12),
+        }
+    }
+
+    fn week_of(&self, date: NaiveDate) -> NaiveDate {
+        date.week(self.week_start).first_day()
+    }
+
+    /// Occurrences in the period containing `date`, sorted.
+    fn period_occurrences(&self, start: NaiveDate, date: NaiveDate) -> Vec<NaiveDate> {
+        let (first, last) = match self.freq {
+            Freq::Daily => (date, date),
+            Freq::Weekly => (self.week_of(date), self.week_of(date) + Duration::days(6)),
+            Freq::Monthly => {
+                let first = date.with_day(1).expect("Invalid date");
+                (
+                    first,
+                    first + Duration::days(i64::from(date.num_days_in_month()) - 1),
+                )
+            }
+            Freq::Yearly => (
+                NaiveDate::from_ymd_opt(date.year(), 1, 1).expect("Invalid date"),
+                NaiveDate::from_ymd_opt(date.year(), 12, 31).expect("Invalid date"),
+            ),
+        };
+        let days: Vec<NaiveDate> = first
+            .iter_days()
+            .take_while(|day| *day <= last)
+            .filter(|day| self.matches(start, *day))
+            .collect();
+        match self.by_set_pos.is_empty() {
+            true => days,
+            false => {
+                let len = days.len() as i32;
+                let mut picked: Vec<NaiveDate> = self
+                    .by_set_pos
+                    .iter()
+                    .map(|pos| if *pos > 0 { pos - 1 } else { len + pos })
+                    .filter(|index| (0..len).contains(index))
+                    .map(|index| days[index as usize])
+                    .collect();
+                picked.sort();
+                picked.dedup();
+                picked
+            }
+        }
+    }
+
+    /// True if `day` passes the `BY` parts, with the defaults taken from `start`.
+    fn matches(&self, start: NaiveDate, day: NaiveDate) -> bool {
+        let month_days = i64::from(day.num_days_in_month());
+        let by_month = match (self.freq, self.by_month.is_empty()) {
+            (_, false) => self.by_month.contains(&day.month()),
+            // yearly rules without days or months happen on the start day
+            (Freq::Yearly, true) if self.by_day.is_empty() && self.by_month_day.is_empty() => {
+                day.month() == start.month()
+            }
+            _ => true,
+        };
+        let by_month_day = match (self.freq, self.by_month_day.is_empty()) {
+            (_, false) => self.by_month_day.iter().any(|n| {
+                let n = i64::from(*n);
+                i64::from(day.day()) == if n > 0 { n } else { month_days + n + 1 }
+            }),
+            (Freq::Monthly | Freq::Yearly, true) if self.by_day.is_empty() => {
+                day.day() == start.day()
+            }
+            _ => true,
+        };
+        let by_day = match (self.freq, self.by_day.is_empty()) {
+            (_, false) => self.by_day.iter().any(|(nth, weekday)| {
+                day.weekday() == *weekday
+                    && nth.is_none_or(|nth| self.weekday_position(day, nth < 0) == i64::from(nth))
+            }),
+            (Freq::Weekly, true) => day.weekday() == start.weekday(),
+            _ => true,
+        };
+        by_month && by_month_day && by_day
+    }
+
+    /// Position of the weekday of `day` in its month, or in its year for yearly rules
+    /// without `BYMONTH`, counted from the end as negative numbers if `from_end`.
+    fn weekday_position(&self, day: NaiveDate, from_end: bool) -> i64 {
+        let in_year = self.freq == Freq::Yearly && self.by_month.is_empty();
+        let (before, total) = match in_year {
+            true => (
+                i64::from(day.ordinal0()),
+                if day.leap_year() { 366 } else { 365 },
+            ),
+            false => (i64::from(day.day0()), i64::from(day.num_days_in_month())),
+        };
+        match from_end {
+            false => before / 7 + 1,
+            true => -((total - before - 1) / 7 + 1),
+        }
+    }
+}
+
+fn split<T>(value: &str, parse: impl Fn(&str) -> Result<T, String>) -> Result<Vec<T>, String> {
+    value.split(',').map(|item| parse(item.trim())).collect()
+}
+
+/// `MO`, `2TU` or `-1SU`.
+fn parse_by_day(value: &str) -> Option<(Option<i8>, Weekday)> {
+    let split = value.len().checked_sub(2)?;
+    let (nth, weekday) = value.split_at_checked(split)?;
+    let nth = match nth {
+        "" => None,
+        nth => match nth.trim_start_matches('+').parse() {
+            Ok(nth @ (-53..=-1 | 1..=53)) => Some(nth),
+            _ => return None,
+        },
+    };
+    Some((nth, parse_weekday(weekday)?))
+}
+
+fn parse_weekday(value: &str) -> Option<Weekday> {
+    match value.to_uppercase().as_str() {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
@@ -0,0 +1,145 @@
+//! Crash-safe writes with rotating backups.
+//!
+//! A save never truncates the live file: the new content goes to a temp file next to it,
+//! which is fsynced and then renamed over the live file. Before that the live file is kept
+//! as `<file>.1`, shifting older backups up to `<file>.<BACKUP_COUNT>`.
+//!
+//! Read-modify-write cycles of several processes are serialized with an advisory lock on
+//! `<file>.lock`. The data file itself can't be locked since every save replaces it.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Number of previous versions kept next to the data file.
+pub const BACKUP_COUNT: usize = 3;
+
+/// A loaded value and the backup it came from when the live file was unreadable.
+#[derive(Debug)]
+pub struct Loaded<T> {
+    pub value: T,
+    pub recovered_from: Option<PathBuf>,
+}
+
+/// Path of the `n`-th newest backup, starting at 1.
+pub fn backup_path(path: &Path, n: usize) -> PathBuf {
+    with_suffix(path, &n.to_string())
+}
+
+pub(crate) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Exclusive advisory lock on a data file, released when dropped.
+#[derive(Debug)]
+pub struct FileLock {
+    _file: fs::File,
+}
+
+/// Path of the lock file of `path`.
+pub fn lock_path(path: &Path) -> PathBuf {
+    with_suffix(path, "lock")
+}
+
+/// Waits until no other process holds the lock of `path`, then takes it.
+pub fn lock(path: &Path) -> io::Result<FileLock> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(lock_path(path))?;
+    file.lock()?;
+    Ok(FileLock { _file: file })
+}
+
+/// True if the file or any of its backups exists.
+pub fn any_version_exists(path: &Path, backups: usize) -> bool {
+    path.exists() || (1..=backups).any(|n| backup_path(path, n).exists())
+}
+
+/// Replaces the content of `path` without ever leaving it truncated.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let tmp = with_suffix(path, &format!("{}.tmp", process::id()));
+    let result = (|| {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&tmp)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)?;
+        sync_parent_dir(path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Keeps the current content of `path` as the newest backup, dropping the oldest one.
+pub fn rotate_backups(path: &Path, backups: usize) -> io::Result<()> {
+    if backups == 0 || !path.exists() {
+        return Ok(());
+    }
+    for n in (1..backups).rev() {
+        let from = backup_path(path, n);
+        if from.exists() {
+            fs::rename(&from, backup_path(path, n + 1))?;
+        }
+    }
+    // the live file has to stay in place until the new one is renamed over it
+    let newest = backup_path(path, 1);
+    if newest.exists() {
+        fs::remove_file(&newest)?;
+    }
+    if fs::hard_link(path, &newest).is_err() {
+        fs::copy(path, &newest)?;
+    }
+    Ok(())
+}
+
+/// Reads `path`, falling back to the newest backup that decodes when it is missing or corrupt.
+pub fn load_with_fallback<T>(
+    path: &Path,
+    backups: usize,
+    decode: impl Fn(&[u8]) -> io::Result<T>,
+) -> io::Result<Loaded<T>> {
+    let error = match fs::read(path).and_then(|bytes| decode(&bytes)) {
+        Ok(value) => {
+            return Ok(Loaded {
+                value,
+                recovered_from: None,
+            })
+        }
+        Err(e) => e,
+    };
+    for n in 1..=backups {
+        let backup = backup_path(path, n);
+        if let Ok(value) = fs::read(&backup).and_then(|bytes| decode(&bytes)) {
+            return Ok(Loaded {
+                value,
+                recovered_from: Some(backup),
+            });
+        }
+    }
+    Err(error)
+}
+
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => fs::File::open(parent)?.sync_all(),
+        _ => fs::File::open(".")?.sync_all(),
+    }
+}
+
+// directories can't be opened for syncing on other platforms, the rename is durable enough
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,721 @@
+//! SQLite storage, enabled with the `sqlite` cargo feature.
+//!
+//! Activities, their completions and their progress live in separate tables, so a
+//! completion is a single row insert and lookups by date use an index instead of scanning
+//! every timestamp.
+
+use crate::activities_manager::{
+    ActivityDetails, ActivityKind, AllActivities, FrequencyType, LocalDay, Pause, Target,
+};
+use crate::data_location;
+use crate::file_storage::FileStorage;
+use crate::safe_file::Loaded;
+use crate::storage::{self, Storage};
+use crate::time_utils;
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension, Transaction, TransactionBehavior};
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+
+/// Version of the schema, kept in `PRAGMA user_version`: [`SCHEMA`] followed by every
+/// step of [`MIGRATIONS`].
+const SCHEMA_VERSION: i64 = 1 + MIGRATIONS.len() as i64;
+
+/// Schema of version 1.
+const SCHEMA: &str = "
+    CREATE TABLE activities (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE,
+        start INTEGER NOT NULL,
+        frequency TEXT NOT NULL
+    );
+    CREATE TABLE completions (
+        activity_id INTEGER NOT NULL REFERENCES activities(id) ON DELETE CASCADE,
+        timestamp INTEGER NOT NULL,
+        day TEXT NOT NULL,
+        PRIMARY KEY (activity_id, timestamp)
+    );
+    CREATE INDEX completions_by_day ON completions(day, activity_id);
+";
+
+/// Steps from each schema version to the next, the first one upgrading version 1. They run
+/// in a single transaction with foreign keys off, so tables can be rebuilt.
+const MIGRATIONS: [&str; 9] = [
+    // quantitative activities
+    "
+    ALTER TABLE activities ADD COLUMN target REAL;
+    ALTER TABLE activities ADD COLUMN unit TEXT;
+    CREATE TABLE progress (
+        activity_id INTEGER NOT NULL REFERENCES activities(id) ON DELETE CASCADE,
+        timestamp INTEGER NOT NULL,
+        amount REAL NOT NULL,
+        PRIMARY KEY (activity_id, timestamp)
+    );
+    ",
+    // activities to avoid
+    "ALTER TABLE activities ADD COLUMN kind TEXT NOT NULL DEFAULT 'do';",
+    // pauses, of the whole tracker when `activity_id` is null
+    "
+    CREATE TABLE pauses (
+        activity_id INTEGER REFERENCES activities(id) ON DELETE CASCADE,
+        first_day TEXT NOT NULL,
+        last_day TEXT NOT NULL
+    );
+    ",
+    // skipped days
+    "
+    CREATE TABLE skips (
+        activity_id INTEGER NOT NULL REFERENCES activities(id) ON DELETE CASCADE,
+        day TEXT NOT NULL,
+        reason TEXT NOT NULL,
+        PRIMARY KEY (activity_id, day)
+    );
+    ",
+    // UTC offset of the completions, whose `day` is the one they were logged for
+    "ALTER TABLE completions ADD COLUMN utc_offset INTEGER;",
+    // day activities were removed on, whose names only have to be unique among the current
+    // ones, so the table is rebuilt without the constraint as SQLite can't drop it
+    "
+    CREATE TABLE new_activities (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        start INTEGER NOT NULL,
+        frequency TEXT NOT NULL,
+        target REAL,
+        unit TEXT,
+        kind TEXT NOT NULL DEFAULT 'do',
+        removed TEXT
+    );
+    INSERT INTO new_activities (id, name, start, frequency, target, unit, kind)
+        SELECT id, name, start, frequency, target, unit, kind FROM activities;
+    DROP TABLE activities;
+    ALTER TABLE new_activities RENAME TO activities;
+    CREATE UNIQUE INDEX current_activity_names ON activities(name) WHERE removed IS NULL;
+    ",
+    // ids that are never reused, not even the one of the newest activity once it's deleted
+    "
+    CREATE TABLE new_activities (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL,
+        start INTEGER NOT NULL,
+        frequency TEXT NOT NULL,
+        target REAL,
+        unit TEXT,
+        kind TEXT NOT NULL DEFAULT 'do',
+        removed TEXT
+    );
+    INSERT INTO new_activities SELECT id, name, start, frequency, target, unit, kind, removed
+        FROM activities;
+    DROP TABLE activities;
+    ALTER TABLE new_activities RENAME TO activities;
+    CREATE UNIQUE INDEX current_activity_names ON activities(name) WHERE removed IS NULL;
+    ",
+    // day and UTC offset progress was logged for, like the ones of completions
+    "
+    ALTER TABLE progress ADD COLUMN day TEXT;
+    ALTER TABLE progress ADD COLUMN utc_offset INTEGER;
+    ",
+    // random id of the database, a single row once it is asked for
+    "CREATE TABLE calendar (id TEXT NOT NULL);",
+];
+
+#[derive(Debug)]
+pub struct SqliteStorage {
+    connection: Connection,
+    existed: bool,
+}
+
+impl SqliteStorage {
+    /// Opens the database at `path`, creating it and its schema if needed.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        data_location::ensure_parent_dir(path)?;
+        Self::init(Connection::open(path).map_err(sql_error)?)
+    }
+
+    /// Opens a database that only lives as long as the storage.
+    pub fn open_in_memory() -> io::Result<Self> {
+        Self::init(Connection::open_in_memory().map_err(sql_error)?)
+    }
+
+    fn init(mut connection: Connection) -> io::Result<Self> {
+        // foreign keys can't be turned off inside a transaction
+        connection
+            .pragma_update(None, "foreign_keys", false)
+            .map_err(sql_error)?;
+        // a migration that fails halfway leaves the schema and its version as they were
+        let transaction = connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(sql_error)?;
+        let version: i64 = transaction
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(sql_error)?;
+        match version {
+            0..SCHEMA_VERSION => {
+                if version == 0 {
+                    transaction.execute_batch(SCHEMA).map_err(sql_error)?;
+                }
+                for migration in &MIGRATIONS[(version.max(1) - 1) as usize..] {
+                    transaction.execute_batch(migration).map_err(sql_error)?;
+                }
+                let broken: bool = transaction
+                    .query_row(
+                        "SELECT EXISTS (SELECT 1 FROM pragma_foreign_key_check)",
+                        [],
+                        |row| row.get(0),
+                    )
+                    .map_err(sql_error)?;
+                if broken {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "migrating the database schema left rows without their activity",
+                    ));
+                }
+                transaction
+                    .pragma_update(None, "user_version", SCHEMA_VERSION)
+                    .map_err(sql_error)?;
+            }
+            SCHEMA_VERSION => {}
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "database schema version {} is newer than the supported version {}",
+                        version, SCHEMA_VERSION
+                    ),
+                ))
+            }
+        }
+        transaction.commit().map_err(sql_error)?;
+        connection
+            .pragma_update(None, "foreign_keys", true)
+            .map_err(sql_error)?;
+        Ok(SqliteStorage {
+            connection,
+            existed: version != 0,
+        })
+    }
+
+    /// One-shot import of a bincode activities file into an empty database.
+    pub fn import_from_file(&mut self, path: &Path) -> io::Result<usize> {
+        if self.has_activities()? {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "the database already has activities, nothing was imported",
+            ));
+        }
+        let activities = FileStorage::new(path.to_path_buf()).load()?.value;
+        self.save(&activities)?;
+        Ok(activities.activities.len())
+    }
+
+    fn has_activities(&self) -> io::Result<bool> {
+        self.connection
+            .query_row("SELECT EXISTS (SELECT 1 FROM activities)", [], |row| {
+                row.get(0)
+            })
+            .map_err(sql_error)
+    }
+
+    fn write<T>(
+        &mut self,
+        change: impl FnOnce(&Transaction) -> rusqlite::Result<T>,
+    ) -> io::Result<T> {
+        let transaction = self.connection.transaction().map_err(sql_error)?;
+        let changed = change(&transaction).map_err(sql_error)?;
+        transaction.commit().map_err(sql_error)?;
+        self.existed = true;
+        Ok(changed)
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn load(&self) -> io::Result<Loaded<AllActivities>> {
+        let mut activities = AllActivities::new();
+        let mut statement = self
+            .connection
+            .prepare(
+                "SELECT id, name, start, frequency, target, unit, kind, removed FROM activities",
+            )
+            .map_err(sql_error)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, Option<f64>>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                    row.get::<_, String>(6)?,
+                    row.get::<_, Option<String>>(7)?,
+                ))
+            })
+            .map_err(sql_error)?;
+        for row in rows {
+            let (id, name, start, frequency, target, unit, kind, removed) =
+                row.map_err(sql_error)?;
+            let target = target.map(|amount| Target {
+                amount,
+                unit: unit.unwrap_or_default(),
+            });
+            let mut activity = ActivityDetails::new_activity(FrequencyType::from_code(&frequency))
+                .with_target(target)
+                .with_kind(match kind == ActivityKind::Avoid.code() {
+                    true => ActivityKind::Avoid,
+                    false => ActivityKind::Do,
+                });
+            activity.name = name;
+            activity.start = start;
+            activity.removed = removed.as_deref().map(parse_day).transpose()?;
+            activities.activities.insert(id as u64, activity);
+            activities.last_id = activities.last_id.max(id as u64);
+        }
+        // deleted activities may have had higher ids
+        let sequence: Option<i64> = self
+            .connection
+            .query_row(
+                "SELECT seq FROM sqlite_sequence WHERE name = 'activities'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sql_error)?;
+        activities.last_id = activities.last_id.max(sequence.unwrap_or(0) as u64);
+
+        let mut statement = self
+            .connection
+            .prepare("SELECT activity_id, timestamp, day, utc_offset FROM completions")
+            .map_err(sql_error)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<i32>>(3)?,
+                ))
+            })
+            .map_err(sql_error)?;
+        for row in rows {
+            let (id, timestamp, day, offset) = row.map_err(sql_error)?;
+            let date = parse_day(&day)?;
+            if let Some(activity) = activities.activities.get_mut(&(id as u64)) {
+                activity.completion_timestamps.insert(timestamp);
+                // completions saved before the offset was kept count for their local day
+                if let Some(offset) = offset {
+                    activity
+                        .completion_days
+                        .insert(timestamp, LocalDay { date, offset });
+                }
+            }
+        }
+
+        let mut statement = self
+            .connection
+            .prepare("SELECT activity_id, timestamp, amount, day, utc_offset FROM progress")
+            .map_err(sql_error)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<i32>>(4)?,
+                ))
+            })
+            .map_err(sql_error)?;
+        for row in rows {
+            let (id, timestamp, amount, day, offset) = row.map_err(sql_error)?;
+            if let Some(activity) = activities.activities.get_mut(&(id as u64)) {
+                activity.progress.insert(timestamp, amount);
+                // progress saved before its day was kept counts for its local day
+                if let (Some(day), Some(offset)) = (day, offset) {
+                    let date = parse_day(&day)?;
+                    activity
+                        .progress_days
+                        .insert(timestamp, LocalDay { date, offset });
+                }
+            }
+        }
+
+        let mut statement = self
+            .connection
+            .prepare("SELECT activity_id, first_day, last_day FROM pauses")
+            .map_err(sql_error)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, Option<i64>>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })
+            .map_err(sql_error)?;
+        for row in rows {
+            let (id, first_day, last_day) = row.map_err(sql_error)?;
+            let pause = Pause {
+                from: parse_day(&first_day)?,
+                until: parse_day(&last_day)?,
+            };
+            match id {
+                Some(id) => {
+                    if let Some(activity) = activities.activities.get_mut(&(id as u64)) {
+                        activity.pauses.push(pause);
+                    }
+                }
+                None => activities.pauses.push(pause),
+            }
+        }
+
+        let mut statement = self
+            .connection
+            .prepare("SELECT activity_id, day, reason FROM skips")
+            .map_err(sql_error)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })
+            .map_err(sql_error)?;
+        for row in rows {
+            let (id, day, reason) = row.map_err(sql_error)?;
+            let day = parse_day(&day)?;
+            if let Some(activity) = activities.activities.get_mut(&(id as u64)) {
+                activity.skips.insert(day, reason);
+            }
+        }
+
+        Ok(Loaded {
+            value: activities,
+            recovered_from: None,
+        })
+    }
+
+    fn save(&mut self, activities: &AllActivities) -> io::Result<()> {
+        self.write(|transaction| {
+            transaction.execute("DELETE FROM activities", [])?;
+            transaction.execute("DELETE FROM pauses", [])?;
+            for (id, activity) in &activities.activities {
+                insert_activity(transaction, Some(*id as i64), activity)?;
+            }
+            for pause in &activities.pauses {
+                insert_pause(transaction, None, pause)?;
+            }
+            transaction.execute("DELETE FROM sqlite_sequence WHERE name = 'activities'", [])?;
+            transaction.execute(
+                "INSERT INTO sqlite_sequence (name, seq) VALUES ('activities', ?1)",
+                params![activities.last_id as i64],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn exists(&self) -> bool {
+        self.existed
+    }
+
+    fn insert_activity(&mut self, activity: &ActivityDetails) -> io::Result<u64> {
+        self.write(|transaction| insert_activity(transaction, None, activity))
+            .map(|id| id as u64)
+    }
+
+    fn edit_activity(&mut self, id: u64, name: &str, frequency: &FrequencyType) -> io::Result<()> {
+        self.write(|transaction| {
+            transaction.execute(
+                "UPDATE activities SET name = ?2, frequency = ?3 WHERE id = ?1",
+                params![id as i64, name, frequency.code()],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn remove_activity(&mut self, id: u64, date: NaiveDate) -> io::Result<()> {
+        self.write(|transaction| {
+            transaction.execute(
+                "UPDATE activities SET removed = ?2 WHERE id = ?1",
+                params![id as i64, date.to_string()],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn delete_activity(&mut self, id: u64) -> io::Result<()> {
+        self.write(|transaction| {
+            transaction.execute("DELETE FROM activities WHERE id = ?1", params![id as i64])?;
+            Ok(())
+        })
+    }
+
+    fn restore_activity(&mut self, id: u64, activity: &ActivityDetails) -> io::Result<()> {
+        self.write(|transaction| {
+            transaction.execute("DELETE FROM activities WHERE id = ?1", params![id as i64])?;
+            insert_activity(transaction, Some(id as i64), activity)?;
+            Ok(())
+        })
+    }
+
+    fn add_completion(&mut self, id: u64, timestamp: i64, day: LocalDay) -> io::Result<()> {
+        self.write(|transaction| {
+            if activity_exists(transaction, id)? {
+                insert_completion(transaction, id as i64, timestamp, Some(day))?;
+            }
+            Ok(())
+        })
+    }
+
+    fn remove_completion(&mut self, id: u64, timestamp: i64) -> io::Result<()> {
+        self.write(|transaction| {
+            transaction.execute(
+                "DELETE FROM completions WHERE activity_id = ?1 AND timestamp = ?2",
+                params![id as i64, timestamp],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn add_progress(
+        &mut self,
+        id: u64,
+        timestamp: i64,
+        amount: f64,
+        day: LocalDay,
+    ) -> io::Result<()> {
+        self.write(|transaction| {
+            if activity_exists(transaction, id)? {
+                insert_progress(transaction, id as i64, timestamp, amount, Some(day))?;
+            }
+            Ok(())
+        })
+    }
+
+    fn remove_progress(&mut self, id: u64, timestamp: i64, amount: f64) -> io::Result<()> {
+        self.write(|transaction| {
+            transaction.execute(
+                "UPDATE progress SET amount = amount - ?3
+                 WHERE activity_id = ?1 AND timestamp = ?2",
+                params![id as i64, timestamp, amount],
+            )?;
+            transaction.execute(
+                "DELETE FROM progress
+                 WHERE activity_id = ?1 AND timestamp = ?2 AND amount < 1e-9",
+                params![id as i64, timestamp],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn add_pause(&mut self, id: Option<u64>, pause: &Pause) -> io::Result<()> {
+        self.write(|transaction| {
+            if let Some(id) = id {
+                if !activity_exists(transaction, id)? {
+                    return Ok(());
+                }
+            }
+            if !pause_exists(transaction, id, pause)? {
+                insert_pause(transaction, id.map(|id| id as i64), pause)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn remove_pause(&mut self, id: Option<u64>, pause: &Pause) -> io::Result<()> {
+        self.write(|transaction| {
+            transaction.execute(
+                "DELETE FROM pauses
+                 WHERE activity_id IS ?1 AND first_day = ?2 AND last_day = ?3",
+                params![
+                    id.map(|id| id as i64),
+                    pause.from.to_string(),
+                    pause.until.to_string()
+                ],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn add_skip(&mut self, id: u64, date: NaiveDate, reason: &str) -> io::Result<()> {
+        self.write(|transaction| {
+            if activity_exists(transaction, id)? {
+                insert_skip(transaction, id as i64, date, reason)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn remove_skip(&mut self, id: u64, date: NaiveDate) -> io::Result<()> {
+        self.write(|transaction| {
+            transaction.execute(
+                "DELETE FROM skips WHERE activity_id = ?1 AND day = ?2",
+                params![id as i64, date.to_string()],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn calendar_id(&mut self) -> io::Result<String> {
+        self.write(|transaction| {
+            transaction.execute(
+                "INSERT INTO calendar (id) SELECT ?1 WHERE NOT EXISTS (SELECT 1 FROM calendar)",
+                params![storage::new_calendar_id()],
+            )?;
+            transaction.query_row("SELECT id FROM calendar", [], |row| row.get(0))
+        })
+    }
+
+    /// Answered from the day index, leaving out the activities removed by then.
+    fn completed_on(&self, date: NaiveDate) -> io::Result<Option<HashSet<u64>>> {
+        let mut statement = self
+            .connection
+            .prepare(
+                "SELECT DISTINCT c.activity_id FROM completions c
+                 JOIN activities a ON a.id = c.activity_id
+                 WHERE c.day = ?1 AND (a.removed IS NULL OR a.removed > ?1)",
+            )
+            .map_err(sql_error)?;
+        let ids = statement
+            .query_map(params![date.to_string()], |row| row.get::<_, i64>(0))
+            .map_err(sql_error)?;
+        let ids = ids.map(|id| id.map(|id| id as u64));
+        ids.collect::<Result<_, _>>().map(Some).map_err(sql_error)
+    }
+}
+
+// changes made elsewhere may have removed the activity since it was loaded
+fn activity_exists(transaction: &Transaction, id: u64) -> rusqlite::Result<bool> {
+    transaction.query_row(
+        "SELECT EXISTS (SELECT 1 FROM activities WHERE id = ?1)",
+        params![id as i64],
+        |row| row.get(0),
+    )
+}
+
+fn pause_exists(
+    transaction: &Transaction,
+    id: Option<u64>,
+    pause: &Pause,
+) -> rusqlite::Result<bool> {
+    transaction.query_row(
+        "SELECT EXISTS (SELECT 1 FROM pauses
+         WHERE activity_id IS ?1 AND first_day = ?2 AND last_day = ?3)",
+        params![
+            id.map(|id| id as i64),
+            pause.from.to_string(),
+            pause.until.to_string()
+        ],
+        |row| row.get(0),
+    )
+}
+
+/// Inserts `activity` under `id`, or under a new id if none, and returns the id.
+fn insert_activity(
+    transaction: &Transaction,
+    id: Option<i64>,
+    activity: &ActivityDetails,
+) -> rusqlite::Result<i64> {
+    let target = activity.target.as_ref();
+    transaction.execute(
+        "INSERT INTO activities (id, name, start, frequency, target, unit, kind, removed)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            id,
+            activity.name,
+            activity.start,
+            activity.frequency.code(),
+            target.map(|target| target.amount),
+            target.map(|target| &target.unit),
+            activity.kind.code(),
+            activity.removed.map(|date| date.to_string()),
+        ],
+    )?;
+    let id = transaction.last_insert_rowid();
+    for timestamp in &activity.completion_timestamps {
+        let day = activity.completion_days.get(timestamp).copied();
+        insert_completion(transaction, id, *timestamp, day)?;
+    }
+    for (timestamp, amount) in &activity.progress {
+        let day = activity.progress_days.get(timestamp).copied();
+        insert_progress(transaction, id, *timestamp, *amount, day)?;
+    }
+    for pause in &activity.pauses {
+        insert_pause(transaction, Some(id), pause)?;
+    }
+    for (date, reason) in &activity.skips {
+        insert_skip(transaction, id, *date, reason)?;
+    }
+    Ok(id)
+}
+
+/// Inserts a completion, counting for the local day of its timestamp without a `day`.
+fn insert_completion(
+    transaction: &Transaction,
+    id: i64,
+    timestamp: i64,
+    day: Option<LocalDay>,
+) -> rusqlite::Result<()> {
+    let date = day.map_or_else(|| time_utils::local_date(timestamp), |day| day.date);
+    transaction.execute(
+        "INSERT OR IGNORE INTO completions (activity_id, timestamp, day, utc_offset)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![id, timestamp, date.to_string(), day.map(|day| day.offset)],
+    )?;
+    Ok(())
+}
+
+fn insert_progress(
+    transaction: &Transaction,
+    id: i64,
+    timestamp: i64,
+    amount: f64,
+    day: Option<LocalDay>,
+) -> rusqlite::Result<()> {
+    transaction.execute(
+        "INSERT INTO progress (activity_id, timestamp, amount, day, utc_offset)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT (activity_id, timestamp) DO UPDATE SET amount = amount + excluded.amount,
+             day = excluded.day, utc_offset = excluded.utc_offset",
+        params![
+            id,
+            timestamp,
+            amount,
+            day.map(|day| day.date.to_string()),
+            day.map(|day| day.offset)
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_pause(transaction: &Transaction, id: Option<i64>, pause: &Pause) -> rusqlite::Result<()> {
+    transaction.execute(
+        "INSERT INTO pauses (activity_id, first_day, last_day) VALUES (?1, ?2, ?3)",
+        params![id, pause.from.to_string(), pause.until.to_string()],
+    )?;
+    Ok(())
+}
+
+fn insert_skip(
+    transaction: &Transaction,
+    id: i64,
+    date: NaiveDate,
+    reason: &str,
+) -> rusqlite::Result<()> {
+    transaction.execute(
+        "INSERT INTO skips (activity_id, day, reason) VALUES (?1, ?2, ?3)
+         ON CONFLICT (activity_id, day) DO UPDATE SET reason = excluded.reason",
+        params![id, date.to_string(), reason],
+    )?;
+    Ok(())
+}
+
+fn parse_day(text: &str) -> io::Result<NaiveDate> {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn sql_error(error: rusqlite::Error) -> io::Error {
+    io::Error::other(error)
+}
@@ -0,0 +1,371 @@
+use crate::activities_manager::{ActivityDetails, AllActivities, FrequencyType, LocalDay, Pause};
+use crate::data_location::ACTIVITIES_FILE;
+use crate::file_storage::FileStorage;
+use crate::safe_file::Loaded;
+#[cfg(feature = "sqlite")]
+use crate::sqlite_storage::SqliteStorage;
+use chrono::NaiveDate;
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::env;
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::path::Path;
+use std::process;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Environment variable that selects the storage backend.
+pub const STORAGE_ENV: &str = "YACHT_STORAGE";
+
+/// Default file name of the SQLite database.
+pub const SQLITE_FILE: &str = "activities.sqlite3";
+
+/// Storage backends that can be selected with `--storage`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StorageKind {
+    /// Single bincode file, see [`crate::file_storage`].
+    #[default]
+    File,
+    /// SQLite database, available with the `sqlite` feature.
+    Sqlite,
+}
+
+impl StorageKind {
+    /// Backend from the `--storage` flag, falling back to `YACHT_STORAGE` and then the file.
+    pub fn resolve(flag: Option<StorageKind>) -> Result<StorageKind, String> {
+        match (flag, env::var(STORAGE_ENV)) {
+            (Some(kind), _) => Ok(kind),
+            (None, Ok(value)) if !value.is_empty() => value.parse(),
+            (None, _) => Ok(StorageKind::default()),
+        }
+    }
+
+    /// Opens the backend with its data at `path`.
+    pub fn open(&self, path: &Path) -> io::Result<Box<dyn Storage>> {
+        match self {
+            StorageKind::File => Ok(Box::new(FileStorage::new(path.to_path_buf()))),
+            #[cfg(feature = "sqlite")]
+            StorageKind::Sqlite => Ok(Box::new(SqliteStorage::open(path)?)),
+            #[cfg(not(feature = "sqlite"))]
+            StorageKind::Sqlite => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "yacht was built without the sqlite feature",
+            )),
+        }
+    }
+
+    /// Default data file name of the backend.
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            StorageKind::File => ACTIVITIES_FILE,
+            StorageKind::Sqlite => SQLITE_FILE,
+        }
+    }
+}
+
+impl FromStr for StorageKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "file" | "bincode" => Ok(StorageKind::File),
+            "sqlite" => Ok(StorageKind::Sqlite),
+            _ => Err(format!(
+                "Unknown storage: {}. Please use file or sqlite.",
+                value
+            )),
+        }
+    }
+}
+
+/// Persistence backend for [`AllActivities`].
+///
+/// `load` and `save` work on every activity at once, the other methods persist a single
+/// change so backends don't have to rewrite everything on each keypress.
+pub trait Storage: Debug {
+    /// Loads every activity, reporting the backup used if the primary copy was unreadable.
+    fn load(&self) -> io::Result<Loaded<AllActivities>>;
+
+    /// Replaces everything stored with `activities`.
+    fn save(&mut self, activities: &AllActivities) -> io::Result<()>;
+
+    /// True if anything was saved before.
+    fn exists(&self) -> bool;
+
+    /// Adds `activity`, whose name is part of it, returning the id the backend gave it.
+    fn insert_activity(&mut self, activity: &ActivityDetails) -> io::Result<u64>;
+
+    /// Changes the name and frequency of an activity, leaving its history as it is.
+    fn edit_activity(&mut self, id: u64, name: &str, frequency: &FrequencyType) -> io::Result<()>;
+
+    /// Removes an activity from `date` on, keeping it and its history for the days before.
+    fn remove_activity(&mut self, id: u64, date: NaiveDate) -> io::Result<()>;
+
+    /// Deletes an activity with its whole history, like an addition that is undone.
+    fn delete_activity(&mut self, id: u64) -> io::Result<()>;
+
+    /// Puts back a removed activity under its old id, with its whole history.
+    fn restore_activity(&mut self, id: u64, activity: &ActivityDetails) -> io::Result<()>;
+
+    /// Adds a completion logged at `timestamp` for the local `day`.
+    fn add_completion(&mut self, id: u64, timestamp: i64, day: LocalDay) -> io::Result<()>;
+
+    fn remove_completion(&mut self, id: u64, timestamp: i64) -> io::Result<()>;
+
+    /// Adds `amount` to the progress of a quantitative activity logged at `timestamp` for
+    /// `day`.
+    fn add_progress(
+        &mut self,
+        id: u64,
+        timestamp: i64,
+        amount: f64,
+        day: LocalDay,
+    ) -> io::Result<()>;
+
+    /// Takes `amount` back from the progress logged at `timestamp`, like a logging undone.
+    fn remove_progress(&mut self, id: u64, timestamp: i64, amount: f64) -> io::Result<()>;
+
+    /// Adds a pause to an activity, or to the whole tracker without an id.
+    fn add_pause(&mut self, id: Option<u64>, pause: &Pause) -> io::Result<()>;
+
+    /// Removes a pause of an activity, or of the whole tracker without an id.
+    fn remove_pause(&mut self, id: Option<u64>, pause: &Pause) -> io::Result<()>;
+
+    /// Skips an activity on `date`, replacing the reason of an earlier skip.
+    fn add_skip(&mut self, id: u64, date: NaiveDate, reason: &str) -> io::Result<()>;
+
+    fn remove_skip(&mut self, id: u64, date: NaiveDate) -> io::Result<()>;
+
+    /// Random id of the stored data, created the first time it is asked for, which keeps the
+    /// calendar `UID`s of its activities apart from the ones of other data files.
+    fn calendar_id(&mut self) -> io::Result<String>;
+
+    /// Ids of the activities with a completion counting for `date`, for backends that can
+    /// look it up in an index. The others leave it to the activities in memory.
+    fn completed_on(&self, _date: NaiveDate) -> io::Result<Option<HashSet<u64>>> {
+        Ok(None)
+    }
+}
+
+/// Storage that keeps everything in memory, for tests and embedding.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    activities: Option<AllActivities>,
+    calendar_id: Option<String>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn activities_mut(&mut self) -> &mut AllActivities {
+        self.activities.get_or_insert_with(AllActivities::new)
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn load(&self) -> io::Result<Loaded<AllActivities>> {
+        Ok(Loaded {
+            value: self.activities.clone().unwrap_or_default(),
+            recovered_from: None,
+        })
+    }
+
+    fn save(&mut self, activities: &AllActivities) -> io::Result<()> {
+        self.activities = Some(activities.clone());
+        Ok(())
+    }
+
+    fn exists(&self) -> bool {
+        self.activities.is_some()
+    }
+
+    fn insert_activity(&mut self, activity: &ActivityDetails) -> io::Result<u64> {
+        Ok(insert_activity(self.activities_mut(), activity))
+    }
+
+    fn edit_activity(&mut self, id: u64, name: &str, frequency: &FrequencyType) -> io::Result<()> {
+        edit_activity(self.activities_mut(), id, name, frequency);
+        Ok(())
+    }
+
+    fn remove_activity(&mut self, id: u64, date: NaiveDate) -> io::Result<()> {
+        remove_activity(self.activities_mut(), id, date);
+        Ok(())
+    }
+
+    fn delete_activity(&mut self, id: u64) -> io::Result<()> {
+        delete_activity(self.activities_mut(), id);
+        Ok(())
+    }
+
+    fn restore_activity(&mut self, id: u64, activity: &ActivityDetails) -> io::Result<()> {
+        restore_activity(self.activities_mut(), id, activity);
+        Ok(())
+    }
+
+    fn add_completion(&mut self, id: u64, timestamp: i64, day: LocalDay) -> io::Result<()> {
+        add_completion(self.activities_mut(), id, timestamp, day);
+        Ok(())
+    }
+
+    fn remove_completion(&mut self, id: u64, timestamp: i64) -> io::Result<()> {
+        remove_completion(self.activities_mut(), id, timestamp);
+        Ok(())
+    }
+
+    fn add_progress(
+        &mut self,
+        id: u64,
+        timestamp: i64,
+        amount: f64,
+        day: LocalDay,
+    ) -> io::Result<()> {
+        add_progress(self.activities_mut(), id, timestamp, amount, day);
+        Ok(())
+    }
+
+    fn remove_progress(&mut self, id: u64, timestamp: i64, amount: f64) -> io::Result<()> {
+        remove_progress(self.activities_mut(), id, timestamp, amount);
+        Ok(())
+    }
+
+    fn add_pause(&mut self, id: Option<u64>, pause: &Pause) -> io::Result<()> {
+        add_pause(self.activities_mut(), id, pause);
+        Ok(())
+    }
+
+    fn remove_pause(&mut self, id: Option<u64>, pause: &Pause) -> io::Result<()> {
+        remove_pause(self.activities_mut(), id, pause);
+        Ok(())
+    }
+
+    fn add_skip(&mut self, id: u64, date: NaiveDate, reason: &str) -> io::Result<()> {
+        add_skip(self.activities_mut(), id, date, reason);
+        Ok(())
+    }
+
+    fn remove_skip(&mut self, id: u64, date: NaiveDate) -> io::Result<()> {
+        remove_skip(self.activities_mut(), id, date);
+        Ok(())
+    }
+
+    fn calendar_id(&mut self) -> io::Result<String> {
+        Ok(self.calendar_id.get_or_insert_with(new_calendar_id).clone())
+    }
+}
+
+/// 16 random hex digits, from the random keys the standard library seeds its hash maps with.
+pub(crate) fn new_calendar_id() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    hasher.write_u32(process::id());
+    format!("{:016x}", hasher.finish())
+}
+
+// single changes applied to a whole snapshot, shared by the snapshot based backends
+
+pub(crate) fn insert_activity(all: &mut AllActivities, activity: &ActivityDetails) -> u64 {
+    all.insert(activity.name.clone(), activity.clone())
+}
+
+pub(crate) fn edit_activity(
+    all: &mut AllActivities,
+    id: u64,
+    name: &str,
+    frequency: &FrequencyType,
+) {
+    if let Some(activity) = all.activities.get_mut(&id) {
+        activity.name = name.to_string();
+        activity.frequency = frequency.clone();
+    }
+}
+
+pub(crate) fn remove_activity(all: &mut AllActivities, id: u64, date: NaiveDate) {
+    if let Some(activity) = all.activities.get_mut(&id) {
+        activity.removed = Some(date);
+    }
+}
+
+pub(crate) fn delete_activity(all: &mut AllActivities, id: u64) {
+    all.activities.remove(&id);
+}
+
+pub(crate) fn restore_activity(all: &mut AllActivities, id: u64, activity: &ActivityDetails) {
+    all.activities.insert(id, activity.clone());
+    all.last_id = all.last_id.max(id);
+}
+
+pub(crate) fn add_completion(all: &mut AllActivities, id: u64, timestamp: i64, day: LocalDay) {
+    if let Some(activity) = all.activities.get_mut(&id) {
+        activity.add_completion(timestamp, day);
+    }
+}
+
+pub(crate) fn remove_completion(all: &mut AllActivities, id: u64, timestamp: i64) {
+    if let Some(activity) = all.activities.get_mut(&id) {
+        activity.remove_completion(timestamp);
+    }
+}
+
+pub(crate) fn add_progress(
+    all: &mut AllActivities,
+    id: u64,
+    timestamp: i64,
+    amount: f64,
+    day: LocalDay,
+) {
+    if let Some(activity) = all.activities.get_mut(&id) {
+        activity.add_progress(timestamp, amount, day);
+    }
+}
+
+pub(crate) fn remove_progress(all: &mut AllActivities, id: u64, timestamp: i64, amount: f64) {
+    if let Some(activity) = all.activities.get_mut(&id) {
+        activity.remove_progress(timestamp, amount);
+    }
+}
+
+fn pauses_mut(all: &mut AllActivities, id: Option<u64>) -> Option<&mut Vec<Pause>> {
+    match id {
+        Some(id) => all
+            .activities
+            .get_mut(&id)
+            .map(|activity| &mut activity.pauses),
+        None => Some(&mut all.pauses),
+    }
+}
+
+pub(crate) fn add_pause(all: &mut AllActivities, id: Option<u64>, pause: &Pause) {
+    if let Some(pauses) = pauses_mut(all, id) {
+        if !pauses.contains(pause) {
+            pauses.push(*pause);
+        }
+    }
+}
+
+pub(crate) fn remove_pause(all: &mut AllActivities, id: Option<u64>, pause: &Pause) {
+    if let Some(pauses) = pauses_mut(all, id) {
+        pauses.retain(|kept| kept != pause);
+    }
+}
+
+pub(crate) fn add_skip(all: &mut AllActivities, id: u64, date: NaiveDate, reason: &str) {
+    if let Some(activity) = all.activities.get_mut(&id) {
+        activity.skips.insert(date, reason.to_string());
+    }
+}
+
+pub(crate) fn remove_skip(all: &mut AllActivities, id: u64, date: NaiveDate) {
+    if let Some(activity) = all.activities.get_mut(&id) {
+        activity.skips.remove(&date);
+    }
+}
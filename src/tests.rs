@@ -1,94 +1,2499 @@
-#[cfg(test)]
-
-mod tests {
-    use crate::activities_manager::FrequencyType;
-    use crate::app::{App, InputMode};
-
-    #[test]
-    fn test_default_app() {
-        let app = App::default();
-        // Verify default values
-        assert_eq!(app.running, true);
-        assert_eq!(app.input, String::new());
-        assert_eq!(app.input_mode, InputMode::Inactive);
+use crate::activities_manager::{ActivityDetails, AllActivities, FrequencyType, LocalDay};
+use crate::app::{App, InputMode};
+use crate::cli::{Cli, Command};
+use crate::data_location::{DataFileSource, DataLocation, ACTIVITIES_FILE};
+use crate::file_format::{self, CURRENT_VERSION, MAGIC};
+use crate::file_storage::FileStorage;
+use crate::interchange::{self, Document, Format, ImportMode};
+use crate::safe_file;
+use crate::storage::{MemoryStorage, Storage, StorageKind};
+use std::ffi::OsString;
+use std::fs;
+use std::path::PathBuf;
+
+#[test]
+fn test_default_app() {
+    let app = App::default();
+    // Verify default values
+    assert!(app.running);
+    assert_eq!(app.input, String::new());
+    assert_eq!(app.input_mode, InputMode::Inactive);
+}
+
+#[test]
+fn test_enter_char_inserts_new_char_at_cursor_position() {
+    let mut app = App {
+        input: String::from("Hello"),
+        cursor_position: 3,
+        ..App::default()
+    };
+
+    app.enter_char('X');
+
+    // Ensure that 'X' is inserted at cursor position
+    assert_eq!(app.input, "HelXlo");
+    // Ensure that cursor position is moved to the right
+    assert_eq!(app.cursor_position, 4);
+}
+
+#[test]
+fn test_enter_non_ascii_char_inserts_new_char_at_cursor_position() {
+    let mut app = App {
+        input: String::from("Veräderung"),
+        cursor_position: 5,
+        ..App::default()
+    };
+
+    app.enter_char('N');
+
+    // Ensure that 'X' is inserted at cursor position
+    assert_eq!(app.input, "VeräNderung");
+    // Ensure that cursor position is moved to the right
+    assert_eq!(app.cursor_position, 6);
+}
+
+pub fn test_remove_added_activity(app: &mut App, activity_name: String) {
+    // Remove the activity
+    if let Some(index) = app
+        .todays_activities
+        .iter()
+        .position(|x| *x == activity_name)
+    {
+        app.remove_activity(index);
+        assert!(!app.all_activities.is_in_activities(activity_name));
+        assert_eq!(app.logs, "Activity is removed!");
+    } else {
+        panic!("Activity not found in todays_activities");
+    }
+}
+
+pub fn test_add_new_activity(app: &mut App, name: String) {
+    let frequency = FrequencyType::AllWeek;
+
+    app.input = name.clone();
+    app.input_mode = InputMode::ActiveName;
+    app.add_new_activity_name();
+    app.new_activity_frequency = frequency;
+
+    app.add_new_activity();
+    //dbg!(app.new_activity_frequency);
+    //dbg!(app.new_activity_name);
+
+    // Ensure that activity is added to all_activities
+    assert!(app.all_activities.is_in_activities(name.clone()));
+    // Ensure that new_activity_name is cleared after adding activity
+    assert_eq!(app.new_activity_name, "");
+    // Ensure that new_activity_frequency is set correctly after adding activity
+    assert_eq!(app.new_activity_frequency, FrequencyType::Invalid);
+    // Ensure that logs indicate successful activity addition
+    assert_eq!(app.logs, "Activity is added!");
+    // Ensure that global status is refreshed after adding activity
+    assert!(!app.day_status); // As it's not a perfect day yet
+    assert_eq!(app.total_perfect_days, 0); // As it's not a perfect day yet
+}
+
+#[test]
+fn test_add_new_activity_and_remove_ascii_and_not() {
+    let mut app = App::default();
+    test_add_new_activity(&mut app, String::from("THINK"));
+    test_remove_added_activity(&mut app, String::from("THINK"));
+    test_add_new_activity(&mut app, String::from("BÜCHER LESEN"));
+    test_remove_added_activity(&mut app, String::from("BÜCHER LESEN"));
+}
+
+#[test]
+fn test_rename_activity_from_app() {
+    let mut app = App::default();
+    test_add_new_activity(&mut app, String::from("THINK"));
+    app.set_activity_done(0);
+    let id = app.all_activities.id_of("THINK").unwrap();
+
+    // only the name changes when the frequency is kept
+    app.start_edit(0);
+    app.delete_char();
+    app.enter_char('g');
+    app.edit_frequency();
+    app.edit_activity();
+    assert_eq!(app.logs, "Activity is edited!");
+    assert_eq!(app.todays_activities, vec![String::from("THING")]);
+    assert_eq!(app.activities_status, vec![String::from("[+]")]);
+    assert!(!app.all_activities.is_in_activities(String::from("THINK")));
+    assert_eq!(app.all_activities.id_of("THING"), Some(id));
+    let thing = &app.all_activities.activities[&id];
+    assert_eq!(thing.frequency, FrequencyType::AllWeek);
+    assert_eq!(thing.completion_timestamps.len(), 1);
+    assert_eq!(app.storage.load().unwrap().value, app.all_activities);
+}
+
+#[test]
+fn test_edit_activity_from_app() {
+    let mut app = App::default();
+    test_add_new_activity(&mut app, String::from("THINK"));
+    app.set_activity_done(0);
+
+    // both steps start from the current values
+    app.start_edit(0);
+    assert_eq!(app.input_mode, InputMode::ActiveEditName);
+    assert_eq!(app.input, "THINK");
+    app.delete_char();
+    app.enter_char('g');
+    app.edit_frequency();
+    assert_eq!(app.input_mode, InputMode::ActiveEditFrequency);
+    assert_eq!(app.input, "AW");
+
+    // validated like new activities
+    app.input = String::from("EVERY OTHER DAY");
+    app.edit_activity();
+    assert!(app.logs.starts_with("Invalid frequency"));
+    assert!(app.all_activities.is_in_activities(String::from("THINK")));
+
+    app.start_edit(0);
+    app.input = String::from("THING");
+    app.edit_frequency();
+    app.input = String::from("3/W");
+    app.edit_activity();
+    assert_eq!(app.logs, "Activity is edited!");
+    assert_eq!(app.todays_activities, vec![String::from("THING")]);
+    assert_eq!(app.activities_status, vec![String::from("[+]")]);
+    let thing = app.all_activities.get("THING").unwrap();
+    assert_eq!(thing.frequency, FrequencyType::from_code("3/W"));
+    assert_eq!(thing.completion_timestamps.len(), 1);
+}
+
+#[test]
+fn test_data_location_precedence() {
+    let flag = Some(PathBuf::from("/flag/activities.bin"));
+    let env_file = Some(OsString::from("/env/activities.bin"));
+    let xdg = Some(OsString::from("/xdg"));
+    let home = Some(OsString::from("/home/user"));
+
+    let location = DataLocation::resolve_from(
+        flag,
+        env_file.clone(),
+        xdg.clone(),
+        home.clone(),
+        ACTIVITIES_FILE,
+    );
+    assert_eq!(location.source, DataFileSource::Flag);
+
+    let location =
+        DataLocation::resolve_from(None, env_file, xdg.clone(), home.clone(), ACTIVITIES_FILE);
+    assert_eq!(location.path, PathBuf::from("/env/activities.bin"));
+    assert_eq!(location.source, DataFileSource::Env);
+
+    let location = DataLocation::resolve_from(None, None, xdg, home.clone(), ACTIVITIES_FILE);
+    assert_eq!(location.path, PathBuf::from("/xdg/yacht/activities.bin"));
+
+    // relative XDG_DATA_HOME is ignored
+    let location = DataLocation::resolve_from(
+        None,
+        None,
+        Some(OsString::from("rel")),
+        home,
+        ACTIVITIES_FILE,
+    );
+    assert_eq!(
+        location.path,
+        PathBuf::from("/home/user/.local/share/yacht/activities.bin")
+    );
+
+    let location = DataLocation::resolve_from(None, None, None, None, "activities.sqlite3");
+    assert_eq!(location.path, PathBuf::from("activities.sqlite3"));
+    assert_eq!(location.source, DataFileSource::WorkingDir);
+}
+
+#[test]
+fn test_legacy_data_file_is_migrated_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let legacy = dir.path().join(ACTIVITIES_FILE);
+    fs::write(&legacy, b"old data").unwrap();
+    let location = DataLocation::resolve_from(
+        None,
+        None,
+        Some(dir.path().join("data").into_os_string()),
+        None,
+        ACTIVITIES_FILE,
+    );
+
+    assert_eq!(
+        location.migrate_from(&legacy).unwrap(),
+        Some(legacy.clone())
+    );
+    assert!(!legacy.exists());
+    assert_eq!(fs::read(&location.path).unwrap(), b"old data");
+
+    // an existing data file is never overwritten
+    fs::write(&legacy, b"other data").unwrap();
+    assert_eq!(location.migrate_from(&legacy).unwrap(), None);
+    assert_eq!(fs::read(&location.path).unwrap(), b"old data");
+}
+
+#[test]
+fn test_cli_data_file_flag() {
+    let args = |args: &[&str]| Cli::parse(args.iter().map(|arg| arg.to_string()));
+    assert_eq!(args(&[]).unwrap(), Cli::default());
+    assert_eq!(
+        args(&["--data-file", "a.bin"]).unwrap().data_file,
+        Some(PathBuf::from("a.bin"))
+    );
+    assert_eq!(
+        args(&["--data-file=b.bin"]).unwrap().data_file,
+        Some(PathBuf::from("b.bin"))
+    );
+    assert!(args(&["--data-file"]).is_err());
+    assert!(args(&["--unknown"]).is_err());
+}
+
+#[test]
+fn test_cli_storage_and_commands() {
+    let args = |args: &[&str]| Cli::parse(args.iter().map(|arg| arg.to_string()));
+    let cli = args(&["--storage", "sqlite", "import-bin", "old.bin"]).unwrap();
+    assert_eq!(cli.storage, Some(StorageKind::Sqlite));
+    assert_eq!(
+        cli.command,
+        Command::ImportBin(Some(PathBuf::from("old.bin")))
+    );
+    assert_eq!(
+        args(&["import-bin"]).unwrap().command,
+        Command::ImportBin(None)
+    );
+    assert!(args(&["--storage=csv"]).is_err());
+    assert!(args(&["import-bin", "a", "b"]).is_err());
+    assert!(args(&["unknown"]).is_err());
+
+    let cli = args(&["import", "a.json", "--mode", "replace", "--dry-run"]).unwrap();
+    assert_eq!(
+        cli.command,
+        Command::Import {
+            path: PathBuf::from("a.json"),
+            format: None,
+            mode: ImportMode::Replace,
+            dry_run: true,
+        }
+    );
+    assert!(args(&["import"]).is_err());
+    assert!(args(&["--dry-run"]).is_err());
+    assert_eq!(
+        args(&["import-loop", "loop.zip", "--dry-run"])
+            .unwrap()
+            .command,
+        Command::ImportLoop {
+            path: PathBuf::from("loop.zip"),
+            mode: ImportMode::Merge,
+            dry_run: true,
+        }
+    );
+    assert!(args(&["import-loop", "loop.zip", "--format", "csv"]).is_err());
+}
+
+// every fixture holds the same activities, written in the layout of its version
+fn assert_fixture_activities(all: &AllActivities) {
+    assert_eq!(all.activities.len(), 3);
+
+    let run = all.get("GO FOR A 2 KM RUN").unwrap();
+    assert_eq!(run.start, 1705708800);
+    assert_eq!(run.frequency, FrequencyType::AllWeek);
+    assert_eq!(
+        run.completion_timestamps,
+        [1705771917, 1706117517].into_iter().collect()
+    );
+
+    let tennis = all.get("PLAY TENNIS").unwrap();
+    assert_eq!(tennis.frequency, FrequencyType::WeekEnds);
+    assert!(tennis.completion_timestamps.is_empty());
+
+    let read = all.get("READ").unwrap();
+    assert_eq!(read.start, 1705795200);
+    assert_eq!(read.frequency, FrequencyType::WorkingDays);
+    assert_eq!(
+        read.completion_timestamps,
+        [1705840000].into_iter().collect()
+    );
+}
+
+#[test]
+fn test_frozen_fixtures_still_load() {
+    use crate::activities_manager::{ActivityKind, Pause, Target, WeekdaySet};
+    use chrono::{NaiveDate, Weekday};
+
+    // removes an activity checked on its own, even a removed one
+    fn take(all: &mut AllActivities, name: &str) -> ActivityDetails {
+        let id = *all
+            .activities
+            .iter()
+            .find(|(_, activity)| activity.name == name)
+            .unwrap()
+            .0;
+        all.activities.remove(&id).unwrap()
+    }
+
+    let fixtures: [&[u8]; 15] = [
+        include_bytes!("../tests/fixtures/activities_v0.bin"),
+        include_bytes!("../tests/fixtures/activities_v1.bin"),
+        include_bytes!("../tests/fixtures/activities_v2.bin"),
+        include_bytes!("../tests/fixtures/activities_v3.bin"),
+        include_bytes!("../tests/fixtures/activities_v4.bin"),
+        include_bytes!("../tests/fixtures/activities_v5.bin"),
+        include_bytes!("../tests/fixtures/activities_v6.bin"),
+        include_bytes!("../tests/fixtures/activities_v7.bin"),
+        include_bytes!("../tests/fixtures/activities_v8.bin"),
+        include_bytes!("../tests/fixtures/activities_v9.bin"),
+        include_bytes!("../tests/fixtures/activities_v10.bin"),
+        include_bytes!("../tests/fixtures/activities_v11.bin"),
+        include_bytes!("../tests/fixtures/activities_v12.bin"),
+        include_bytes!("../tests/fixtures/activities_v13.bin"),
+        include_bytes!("../tests/fixtures/activities_v14.bin"),
+    ];
+    let pause = |code| Pause::from_code(code, NaiveDate::MIN).unwrap();
+    for (version, bytes) in fixtures.iter().enumerate() {
+        assert_eq!(file_format::split_header(bytes).unwrap().0, version as u16);
+        let mut all = file_format::decode(bytes).unwrap();
+        // name-keyed files get ids in the order of the names, later ones were added after
+        let mut by_id: Vec<_> = all.activities.iter().collect();
+        by_id.sort_by_key(|(id, _)| **id);
+        let names: Vec<&str> = by_id.iter().map(|(_, a)| a.name.as_str()).collect();
+        let mut sorted = names.clone();
+        sorted.sort();
+        for added in [
+            (9, "MEDITATE"),
+            (10, "STUDY"),
+            (11, "YOGA"),
+            (12, "JOURNAL"),
+            (13, "PIANO"),
+            (14, "WALK"),
+        ] {
+            if version >= added.0 {
+                sorted.retain(|name| *name != added.1);
+                sorted.push(added.1);
+            }
+        }
+        assert_eq!(names, sorted);
+        assert_eq!(all.last_id, names.len() as u64);
+        // later versions add activities using what they introduced
+        if version >= 2 {
+            let gym = take(&mut all, "GYM");
+            assert_eq!(
+                gym.frequency,
+                FrequencyType::Weekdays(WeekdaySet::new([
+                    Weekday::Mon,
+                    Weekday::Wed,
+                    Weekday::Fri
+                ]))
+            );
+        }
+        if version >= 3 {
+            let swim = take(&mut all, "SWIM");
+            assert_eq!(swim.frequency, FrequencyType::from_code("3/W"));
+            assert_eq!(
+                swim.completion_timestamps,
+                [1705840000].into_iter().collect()
+            );
+        }
+        if version >= 4 {
+            let water = take(&mut all, "WATER PLANTS");
+            assert_eq!(water.frequency, FrequencyType::from_code("3D"));
+            assert_eq!(
+                water.completion_timestamps,
+                [1705771917].into_iter().collect()
+            );
+        }
+        if version >= 5 {
+            let budget = take(&mut all, "BUDGET REVIEW");
+            assert_eq!(budget.frequency, FrequencyType::from_code("LAST SUN"));
+            assert_eq!(
+                budget.completion_timestamps,
+                [1706457600].into_iter().collect()
+            );
+        }
+        if version >= 6 {
+            let clean = take(&mut all, "DEEP CLEAN");
+            assert_eq!(
+                clean.frequency,
+                FrequencyType::Rrule(String::from("FREQ=WEEKLY;INTERVAL=2;BYDAY=TU"))
+            );
+            assert_eq!(clean.start, 1705708800);
+        }
+        if version >= 7 {
+            let water = take(&mut all, "DRINK WATER");
+            assert_eq!(water.target, Target::from_code("8 glasses"));
+            assert_eq!(
+                water.progress,
+                [(1705771917, 3.0), (1705780000, 5.0)].into_iter().collect()
+            );
+            assert_eq!(
+                water.completion_timestamps,
+                [1705780000].into_iter().collect()
+            );
+        }
+        if version >= 8 {
+            let sugar = take(&mut all, "NO SUGAR");
+            assert_eq!(sugar.kind, ActivityKind::Avoid);
+            assert_eq!(
+                sugar.completion_timestamps,
+                [1705840000].into_iter().collect()
+            );
+        }
+        if version >= 10 {
+            let study = take(&mut all, "STUDY");
+            assert_eq!(study.frequency, FrequencyType::WorkingDays);
+            assert_eq!(study.pauses, [pause("2024-01-22..2024-01-26")]);
+            assert_eq!(all.pauses, [pause("2024-02-01..2024-02-04")]);
+            all.pauses.clear();
+        }
+        if version >= 11 {
+            let yoga = take(&mut all, "YOGA");
+            assert_eq!(yoga.frequency, FrequencyType::AllWeek);
+            assert_eq!(
+                yoga.skips,
+                [(
+                    NaiveDate::from_ymd_opt(2024, 1, 21).unwrap(),
+                    String::from("SORE BACK")
+                )]
+                .into_iter()
+                .collect()
+            );
+        }
+        if version >= 12 {
+            let journal = take(&mut all, "JOURNAL");
+            let day = LocalDay {
+                date: NaiveDate::from_ymd_opt(2024, 1, 22).unwrap(),
+                offset: 3600,
+            };
+            assert_eq!(journal.completion_day(1705969800), day);
+            assert!(journal.is_done_on(day.date));
+        }
+        if version >= 13 {
+            assert_eq!(all.id_of("PIANO"), None);
+            let piano = take(&mut all, "PIANO");
+            assert_eq!(piano.removed, NaiveDate::from_ymd_opt(2024, 1, 25));
+            assert!(piano.exists_on(NaiveDate::from_ymd_opt(2024, 1, 24).unwrap()));
+            assert!(!piano.exists_on(NaiveDate::from_ymd_opt(2024, 1, 25).unwrap()));
+        }
+        if version >= 14 {
+            let walk = take(&mut all, "WALK");
+            assert_eq!(walk.target, Target::from_code("2 km"));
+            let day = LocalDay {
+                date: NaiveDate::from_ymd_opt(2024, 1, 22).unwrap(),
+                offset: 3600,
+            };
+            assert_eq!(walk.progress_day(1705969800), day);
+            assert_eq!(walk.progress_on(day.date), 0.5);
+        }
+        assert!(all.pauses.is_empty());
+        for activity in all.activities.values() {
+            assert!(activity.pauses.is_empty());
+            assert!(activity.skips.is_empty());
+            assert!(activity.completion_days.is_empty());
+            assert_eq!(activity.removed, None);
+            assert_eq!(activity.kind, ActivityKind::Do);
+            assert_eq!(activity.target, None);
+            assert!(activity.progress.is_empty());
+            assert!(activity.progress_days.is_empty());
+        }
+        if version >= 9 {
+            let meditate = take(&mut all, "MEDITATE");
+            assert_eq!(meditate.frequency, FrequencyType::AllWeek);
+            assert_eq!(
+                meditate.completion_timestamps,
+                [1705840000].into_iter().collect()
+            );
+        }
+        assert_fixture_activities(&all);
+    }
+}
+
+#[test]
+fn test_file_format_round_trip() {
+    let mut all = AllActivities::new();
+    all.insert(
+        String::from("THINK"),
+        ActivityDetails::new_activity(FrequencyType::WorkingDays),
+    );
+    let bytes = file_format::encode(&all).unwrap();
+
+    assert!(bytes.starts_with(MAGIC));
+    assert_eq!(
+        file_format::split_header(&bytes).unwrap().0,
+        CURRENT_VERSION
+    );
+    assert_eq!(
+        file_format::decode(&bytes).unwrap().activities,
+        all.activities
+    );
+}
+
+#[test]
+fn test_file_format_rejects_newer_versions() {
+    let mut bytes = Vec::from(&MAGIC[..]);
+    bytes.extend_from_slice(&(CURRENT_VERSION + 1).to_le_bytes());
+    assert!(file_format::decode(&bytes).is_err());
+    // truncated header
+    assert!(file_format::decode(&MAGIC[..]).is_err());
+}
+
+fn decode_text(bytes: &[u8]) -> std::io::Result<String> {
+    String::from_utf8(bytes.to_vec()).map_err(std::io::Error::other)
+}
+
+fn save_text(path: &std::path::Path, text: &str) {
+    safe_file::rotate_backups(path, 2).unwrap();
+    safe_file::write_atomic(path, text.as_bytes()).unwrap();
+}
+
+#[test]
+fn test_atomic_save_keeps_rotating_backups() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(ACTIVITIES_FILE);
+    for text in ["first", "second", "third", "fourth"] {
+        save_text(&path, text);
+    }
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "fourth");
+    assert_eq!(
+        fs::read_to_string(safe_file::backup_path(&path, 1)).unwrap(),
+        "third"
+    );
+    assert_eq!(
+        fs::read_to_string(safe_file::backup_path(&path, 2)).unwrap(),
+        "second"
+    );
+    assert!(!safe_file::backup_path(&path, 3).exists());
+    // no temp files are left behind
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+}
+
+#[test]
+fn test_load_falls_back_to_newest_readable_backup() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(ACTIVITIES_FILE);
+    for text in ["first", "second", "third"] {
+        save_text(&path, text);
+    }
+
+    let loaded = safe_file::load_with_fallback(&path, 2, decode_text).unwrap();
+    assert_eq!(loaded.value, "third");
+    assert_eq!(loaded.recovered_from, None);
+
+    // invalid utf-8 stands in for a corrupt file
+    fs::write(&path, [0xff]).unwrap();
+    fs::write(safe_file::backup_path(&path, 1), [0xff]).unwrap();
+    let loaded = safe_file::load_with_fallback(&path, 2, decode_text).unwrap();
+    assert_eq!(loaded.value, "first");
+    assert_eq!(
+        loaded.recovered_from,
+        Some(safe_file::backup_path(&path, 2))
+    );
+
+    fs::remove_file(safe_file::backup_path(&path, 2)).unwrap();
+    assert!(safe_file::load_with_fallback(&path, 2, decode_text).is_err());
+
+    // single changes aren't applied to a backup over the unreadable file
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(ACTIVITIES_FILE);
+    let mut storage = FileStorage::new(path.clone());
+    let mut all = AllActivities::new();
+    all.insert(
+        String::from("READ"),
+        ActivityDetails::new_activity(FrequencyType::AllWeek),
+    );
+    storage.save(&all).unwrap();
+    storage.save(&all).unwrap();
+    fs::write(&path, [0xff]).unwrap();
+    assert_eq!(storage.load().unwrap().value, all);
+    let error = storage
+        .remove_activity(1, crate::time_utils::current_date())
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(fs::read(&path).unwrap(), [0xff]);
+}
+
+#[test]
+fn test_concurrent_file_saves_keep_every_change() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(ACTIVITIES_FILE);
+    let mut all = AllActivities::new();
+    all.insert(
+        String::from("THINK"),
+        ActivityDetails::new_activity(FrequencyType::AllWeek),
+    );
+    FileStorage::new(path.clone()).save(&all).unwrap();
+
+    // two instances completing at the same time each reload what is on disk under the lock
+    let writers: Vec<_> = (0..2)
+        .map(|writer| {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                let mut storage = FileStorage::new(path);
+                for n in 0..20 {
+                    let timestamp = writer * 100 + n;
+                    let day = LocalDay::of(timestamp);
+                    storage.add_completion(1, timestamp, day).unwrap();
+                }
+            })
+        })
+        .collect();
+    for writer in writers {
+        writer.join().unwrap();
+    }
+    let loaded = FileStorage::new(path.clone()).load().unwrap().value;
+    assert_eq!(loaded.get("THINK").unwrap().completion_timestamps.len(), 40);
+    assert!(safe_file::lock_path(&path).exists());
+}
+
+#[test]
+fn test_pauses_of_other_instances_are_kept() {
+    use crate::activities_manager::Pause;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(ACTIVITIES_FILE);
+    let mut all = AllActivities::new();
+    all.insert(
+        String::from("THINK"),
+        ActivityDetails::new_activity(FrequencyType::AllWeek),
+    );
+    FileStorage::new(path.clone()).save(&all).unwrap();
+
+    // two instances loaded the same activities, each adds a pause
+    let pause = |code| Pause::from_code(code, chrono::NaiveDate::MIN).unwrap();
+    let (mut first, mut second) = (
+        FileStorage::new(path.clone()),
+        FileStorage::new(path.clone()),
+    );
+    let (mut first_all, mut second_all) = (first.load().unwrap().value, all.clone());
+    first_all
+        .pause(&mut first, None, pause("2024-02-01..2024-02-04"))
+        .unwrap();
+    first_all
+        .pause(&mut first, Some(String::from("THINK")), pause("2024-01-22"))
+        .unwrap();
+    second_all
+        .pause(&mut second, None, pause("2024-03-01..2024-03-02"))
+        .unwrap();
+    let loaded = FileStorage::new(path).load().unwrap().value;
+    assert_eq!(
+        loaded.pauses,
+        [
+            pause("2024-02-01..2024-02-04"),
+            pause("2024-03-01..2024-03-02")
+        ]
+    );
+    assert_eq!(loaded.get("THINK").unwrap().pauses, [pause("2024-01-22")]);
+}
+
+#[test]
+fn test_reload_picks_up_external_changes() {
+    let mut storage = MemoryStorage::new();
+    let mut all = AllActivities::new();
+    for name in ["READ", "THINK"] {
+        all.add_activity(
+            &mut storage,
+            String::from(name),
+            ActivityDetails::new_activity(FrequencyType::AllWeek),
+        )
+        .unwrap();
+    }
+    let mut app = App::new(Box::new(storage));
+    app.selected = 1;
+    app.reload();
+    assert!(app.logs.starts_with("Today is"));
+
+    // another instance completes THINK and removes READ
+    let now = crate::time_utils::current_time_timestamp();
+    let id = |name| app.all_activities.id_of(name).unwrap();
+    let (think, read) = (id("THINK"), id("READ"));
+    app.storage
+        .add_completion(think, now, LocalDay::of(now))
+        .unwrap();
+    app.storage
+        .remove_activity(read, crate::time_utils::current_date())
+        .unwrap();
+    app.reload();
+    assert_eq!(app.todays_activities, vec![String::from("THINK")]);
+    assert_eq!(app.activities_status, vec![String::from("[+]")]);
+    assert_eq!(app.selected, 0);
+    assert!(app.day_status);
+    assert_eq!(app.logs, "Activities were changed elsewhere and reloaded");
+}
+
+fn check_storage_backend(storage: &mut dyn Storage) {
+    use crate::activities_manager::{Pause, Target};
+
+    assert!(!storage.exists());
+    let mut all = AllActivities::new();
+    all.add_activity(
+        storage,
+        String::from("THINK"),
+        ActivityDetails::new_activity(FrequencyType::AllWeek),
+    )
+    .unwrap();
+    all.add_activity(
+        storage,
+        String::from("WALK"),
+        ActivityDetails::new_activity(FrequencyType::AllWeek),
+    )
+    .unwrap();
+    all.add_activity(
+        storage,
+        String::from("RUN"),
+        ActivityDetails::new_activity(FrequencyType::AllWeek)
+            .with_target(Target::from_code("2 km")),
+    )
+    .unwrap();
+    all.set_activity_done(storage, String::from("THINK"))
+        .unwrap();
+    all.remove_activity(storage, String::from("WALK")).unwrap();
+    assert_eq!(
+        all.log_progress(storage, String::from("RUN"), 1.2),
+        Ok(false)
+    );
+    assert_eq!(
+        all.log_progress(storage, String::from("RUN"), 0.8),
+        Ok(true)
+    );
+
+    assert!(storage.exists());
+    let loaded = storage.load().unwrap();
+    assert_eq!(loaded.value.activities, all.activities);
+    assert_eq!(
+        loaded
+            .value
+            .get("THINK")
+            .unwrap()
+            .completion_timestamps
+            .len(),
+        1
+    );
+    assert!(!loaded.value.is_in_activities(String::from("WALK")));
+    assert_eq!(
+        loaded.value.get("RUN").unwrap().completion_timestamps.len(),
+        1
+    );
+
+    // pauses of an activity and of the whole tracker
+    let pause = |code| Pause::from_code(code, chrono::NaiveDate::MIN).unwrap();
+    all.pause(
+        storage,
+        Some(String::from("THINK")),
+        pause("2024-01-22..2024-01-26"),
+    )
+    .unwrap();
+    all.pause(storage, None, pause("2024-02-01..2024-02-04"))
+        .unwrap();
+
+    // skips keep their reason
+    all.add_activity(
+        storage,
+        String::from("STRETCH"),
+        ActivityDetails::new_activity(FrequencyType::AllWeek),
+    )
+    .unwrap();
+    all.skip_activity(storage, String::from("STRETCH"), String::from("SICK"))
+        .unwrap();
+
+    // undo puts back a removed activity under its id and takes back a completion
+    let think = all.id_of("THINK").unwrap();
+    let removed = all.activities[&think].clone();
+    all.remove_activity(storage, String::from("THINK")).unwrap();
+    storage.restore_activity(think, &removed).unwrap();
+    all.activities.insert(think, removed);
+    let timestamp = *all.activities[&think]
+        .completion_timestamps
+        .iter()
+        .next()
+        .unwrap();
+    storage.remove_completion(think, timestamp).unwrap();
+    all.activities
+        .get_mut(&think)
+        .unwrap()
+        .remove_completion(timestamp);
+
+    // undo takes back progress and skips
+    let run = all.id_of("RUN").unwrap();
+    let timestamp = *all.activities[&run].progress.keys().next_back().unwrap();
+    storage.remove_progress(run, timestamp, 0.8).unwrap();
+    crate::storage::remove_progress(&mut all, run, timestamp, 0.8);
+    let stretch = all.id_of("STRETCH").unwrap();
+    let today = crate::time_utils::current_date();
+    storage.remove_skip(stretch, today).unwrap();
+    crate::storage::remove_skip(&mut all, stretch, today);
+
+    // editing keeps the id and the history, and is checked like adding
+    let id = all.id_of("RUN");
+    let edit = |all: &mut AllActivities, storage: &mut dyn Storage, name: &str, code: &str| {
+        all.edit_activity(
+            storage,
+            String::from("RUN"),
+            String::from(name),
+            FrequencyType::from_code(code),
+        )
+    };
+    assert_eq!(
+        edit(&mut all, storage, "THINK", "AW"),
+        Err("Activity Already Exists")
+    );
+    assert_eq!(
+        edit(&mut all, storage, " ", "AW"),
+        Err("Name Can't Be Empty")
+    );
+    assert_eq!(
+        edit(&mut all, storage, "RUN", "SOMEDAY"),
+        Err("Wrong Frequency Type")
+    );
+    edit(&mut all, storage, "Go for a run", "WD").unwrap();
+    let loaded = storage.load().unwrap().value;
+    assert_eq!(loaded, all);
+    assert_eq!(loaded.id_of("GO FOR A RUN"), id);
+    assert!(!loaded.is_in_activities(String::from("RUN")));
+    let run = loaded.get("GO FOR A RUN").unwrap();
+    assert_eq!(run.frequency, FrequencyType::WorkingDays);
+    assert_eq!(run.completion_timestamps.len(), 1);
+    assert!((run.progress.values().sum::<f64>() - 1.2).abs() < 1e-9);
+
+    // the calendar id is made once and kept through a save
+    let calendar_id = storage.calendar_id().unwrap();
+    assert_eq!(calendar_id.len(), 16);
+    storage.save(&loaded).unwrap();
+    assert_eq!(storage.calendar_id().unwrap(), calendar_id);
+}
+
+#[test]
+fn test_storage_backends_persist_single_changes() {
+    check_storage_backend(&mut MemoryStorage::new());
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data").join(ACTIVITIES_FILE);
+    let mut storage = FileStorage::new(path.clone());
+    check_storage_backend(&mut storage);
+    // a fresh instance sees the same activities and calendar id, another data file has its own
+    let mut reopened = FileStorage::new(path);
+    assert!(reopened
+        .load()
+        .unwrap()
+        .value
+        .is_in_activities(String::from("THINK")));
+    let calendar_id = storage.calendar_id().unwrap();
+    assert_eq!(reopened.calendar_id().unwrap(), calendar_id);
+    let mut other = FileStorage::new(dir.path().join("other").join(ACTIVITIES_FILE));
+    assert_ne!(other.calendar_id().unwrap(), calendar_id);
+    assert_ne!(MemoryStorage::new().calendar_id().unwrap(), calendar_id);
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_sqlite_backend_and_bincode_import() {
+    use crate::activities_manager::Day;
+    use crate::sqlite_storage::SqliteStorage;
+    use chrono::NaiveDate;
+    use std::collections::HashSet;
+
+    check_storage_backend(&mut SqliteStorage::open_in_memory().unwrap());
+
+    let dir = tempfile::tempdir().unwrap();
+    let bincode_file = dir.path().join(ACTIVITIES_FILE);
+    fs::write(
+        &bincode_file,
+        include_bytes!("../tests/fixtures/activities_v1.bin"),
+    )
+    .unwrap();
+    let database = dir.path().join("activities.sqlite3");
+    let mut storage = SqliteStorage::open(&database).unwrap();
+    assert_eq!(storage.import_from_file(&bincode_file).unwrap(), 3);
+    // the import is one-shot
+    assert!(storage.import_from_file(&bincode_file).is_err());
+
+    let mut storage = SqliteStorage::open(&database).unwrap();
+    assert!(storage.exists());
+    assert_eq!(storage.load().unwrap().value.activities.len(), 3);
+    assert_fixture_activities(&storage.load().unwrap().value);
+
+    // the day index gives the same day as the completions in memory
+    let all = storage.load().unwrap().value;
+    let read = all.id_of("READ").unwrap();
+    let date = crate::time_utils::local_date(1705840000);
+    let completed = storage.completed_on(date).unwrap().unwrap();
+    assert_eq!(completed, [read].into_iter().collect());
+    let adjustment = (date - crate::time_utils::current_date()).num_days();
+    assert_eq!(
+        Day::get_day_from(&all, adjustment, Some(&completed)).today_activities_list(),
+        Day::get_day(&all, adjustment).today_activities_list()
+    );
+    assert_eq!(
+        storage
+            .completed_on(NaiveDate::from_ymd_opt(2020, 1, 1).unwrap())
+            .unwrap(),
+        Some(HashSet::new())
+    );
+    // activities removed by then are left out
+    storage.remove_activity(read, date).unwrap();
+    assert_eq!(storage.completed_on(date).unwrap(), Some(HashSet::new()));
+    assert!(MemoryStorage::new().completed_on(date).unwrap().is_none());
+
+    // databases of schema version 1 are migrated when opened
+    let old_database = dir.path().join("old.sqlite3");
+    rusqlite::Connection::open(&old_database)
+        .unwrap()
+        .execute_batch(
+            "CREATE TABLE activities (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                start INTEGER NOT NULL,
+                frequency TEXT NOT NULL
+            );
+            CREATE TABLE completions (
+                activity_id INTEGER NOT NULL REFERENCES activities(id) ON DELETE CASCADE,
+                timestamp INTEGER NOT NULL,
+                day TEXT NOT NULL,
+                PRIMARY KEY (activity_id, timestamp)
+            );
+            INSERT INTO activities (name, start, frequency) VALUES ('READ', 1705795200, 'WD');
+            PRAGMA user_version = 1;",
+        )
+        .unwrap();
+    let mut storage = SqliteStorage::open(&old_database).unwrap();
+    assert_eq!(
+        storage.load().unwrap().value.get("READ").unwrap().frequency,
+        FrequencyType::WorkingDays
+    );
+    let day = LocalDay {
+        date: NaiveDate::from_ymd_opt(2024, 1, 20).unwrap(),
+        offset: -3600,
+    };
+    storage.add_progress(1, 1705840000, 20.0, day).unwrap();
+    let read = storage.load().unwrap().value.get("READ").unwrap().clone();
+    assert_eq!(read.progress[&1705840000], 20.0);
+    assert_eq!(read.progress_day(1705840000), day);
+
+    // a migration failing halfway leaves the database as it was
+    let broken_database = dir.path().join("broken.sqlite3");
+    rusqlite::Connection::open(&broken_database)
+        .unwrap()
+        .execute_batch(
+            "CREATE TABLE activities (id INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE,
+                start INTEGER NOT NULL, frequency TEXT NOT NULL);
+            CREATE TABLE pauses (note TEXT);
+            PRAGMA user_version = 1;",
+        )
+        .unwrap();
+    assert!(SqliteStorage::open(&broken_database).is_err());
+    let connection = rusqlite::Connection::open(&broken_database).unwrap();
+    let version: i64 = connection
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .unwrap();
+    assert_eq!(version, 1);
+    assert!(connection.prepare("SELECT target FROM activities").is_err());
+
+    // the rebuilt table keeps the completions, and names are only unique among current ones
+    let removed = chrono::NaiveDate::from_ymd_opt(2024, 1, 25).unwrap();
+    storage
+        .add_completion(1, 1705840000, LocalDay::of(1705840000))
+        .unwrap();
+    storage.remove_activity(1, removed).unwrap();
+    let mut read = ActivityDetails::new_activity(FrequencyType::AllWeek);
+    read.name = String::from("READ");
+    assert!(storage.insert_activity(&read).is_ok());
+    assert!(storage.insert_activity(&read).is_err());
+    let loaded = storage.load().unwrap().value;
+    assert_eq!(loaded.activities[&1].removed, Some(removed));
+    assert!(loaded.activities[&1].is_done_on(LocalDay::of(1705840000).date));
+    assert_eq!(
+        loaded.get("READ").unwrap().frequency,
+        FrequencyType::AllWeek
+    );
+
+    // ids stay unique even once the newest activity is deleted, and through a save
+    let newest = loaded.id_of("READ").unwrap();
+    storage.delete_activity(newest).unwrap();
+    assert_eq!(storage.load().unwrap().value.last_id, newest);
+    let id = storage.insert_activity(&read).unwrap();
+    assert_eq!(id, newest + 1);
+    let mut saved = storage.load().unwrap().value;
+    saved.last_id = 10;
+    storage.save(&saved).unwrap();
+    storage.delete_activity(id).unwrap();
+    assert_eq!(storage.insert_activity(&read).unwrap(), 11);
+}
+
+fn fixture_activities() -> AllActivities {
+    file_format::decode(include_bytes!("../tests/fixtures/activities_v1.bin")).unwrap()
+}
+
+#[test]
+fn test_export_round_trips_json_and_toml() {
+    let all = fixture_activities();
+    // completions of older files come back with the day of their timestamp recorded
+    let mut expected = all.activities.clone();
+    for activity in expected.values_mut() {
+        for timestamp in activity.completion_timestamps.clone() {
+            let day = activity.completion_day(timestamp);
+            activity.add_completion(timestamp, day);
+        }
     }
+    for format in [Format::Json, Format::Toml] {
+        let text = Document::from_activities(&all).render(format).unwrap();
+        let imported = Document::parse(&text, format)
+            .unwrap()
+            .to_activities()
+            .unwrap();
+        assert_eq!(imported.activities, expected);
+    }
+
+    // pauses of the activities and of the whole tracker are kept too, and so are skips
+    let all = file_format::decode(include_bytes!("../tests/fixtures/activities_v11.bin")).unwrap();
+    let text = Document::from_activities(&all)
+        .render(Format::Toml)
+        .unwrap();
+    let imported = Document::parse(&text, Format::Toml)
+        .unwrap()
+        .to_activities()
+        .unwrap();
+    assert_eq!(imported.pauses, all.pauses);
+    assert_eq!(
+        imported.get("STUDY").unwrap().pauses,
+        all.get("STUDY").unwrap().pauses
+    );
+    assert_eq!(
+        imported.get("YOGA").unwrap().skips,
+        all.get("YOGA").unwrap().skips
+    );
+    let open_ended = "version = 1\npauses = [\"2024-02-01\"]\nactivities = []";
+    assert_eq!(
+        Document::parse(open_ended, Format::Toml)
+            .unwrap()
+            .to_activities()
+            .unwrap_err()
+            .to_string(),
+        "tracker: invalid pause \"2024-02-01\""
+    );
+    assert_eq!(
+        Format::from_path(std::path::Path::new("habits.TOML")),
+        Some(Format::Toml)
+    );
+}
+
+#[test]
+fn test_import_merge_and_replace_plans() {
+    let current = fixture_activities();
+    let document = Document::parse(
+        r#"
+        version = 1
+
+        [[activities]]
+        name = "read"
+        start = "2024-01-21T00:00:00Z"
+        frequency = "AW"
+        completions = ["2024-01-25T10:00:00+01:00"]
 
-    #[test]
-    fn test_enter_char_inserts_new_char_at_cursor_position() {
-        let mut app = App::default();
-        app.input = String::from("Hello");
-        app.cursor_position = 3;
+        [[activities]]
+        name = "Swim"
+        start = "2024-02-01T00:00:00Z"
+        frequency = "WE"
+        "#,
+        Format::Toml,
+    )
+    .unwrap();
+    let imported = document.to_activities().unwrap();
 
-        app.enter_char('X');
+    let merge = interchange::plan_import(&current, &imported, ImportMode::Merge);
+    assert_eq!(
+        merge.changes,
+        [
+            "~ READ: 1 completions added",
+            "+ SWIM: new WE activity with 0 completions",
+        ]
+    );
+    assert_eq!(merge.activities.activities.len(), 4);
+    // a merge only adds, the imported frequency is left to a replace
+    assert_eq!(
+        merge.activities.get("READ").unwrap().frequency,
+        FrequencyType::WorkingDays
+    );
+    assert_eq!(
+        merge
+            .activities
+            .get("READ")
+            .unwrap()
+            .completion_timestamps
+            .len(),
+        2
+    );
 
-        // Ensure that 'X' is inserted at cursor position
-        assert_eq!(app.input, "HelXlo");
-        // Ensure that cursor position is moved to the right
-        assert_eq!(app.cursor_position, 4);
+    let replace = interchange::plan_import(&current, &imported, ImportMode::Replace);
+    assert_eq!(replace.changes.len(), 4);
+    assert!(replace.changes.contains(&String::from(
+        "~ READ: frequency WD -> AW, 1 completions added, 1 completions removed"
+    )));
+    assert!(replace
+        .changes
+        .contains(&String::from("- PLAY TENNIS: removed with 0 completions")));
+    assert_eq!(replace.activities.activities.len(), 2);
+    // planning never touches the current activities
+    assert_eq!(current.activities, fixture_activities().activities);
+}
+
+#[test]
+fn test_removed_activities_survive_a_backup_and_restore() {
+    let mut all = fixture_activities();
+    for activity in all.activities.values_mut() {
+        for timestamp in activity.completion_timestamps.clone() {
+            let day = activity.completion_day(timestamp);
+            activity.add_completion(timestamp, day);
+        }
     }
+    let tennis = all.id_of("PLAY TENNIS").unwrap();
+    let removed = chrono::NaiveDate::from_ymd_opt(2024, 1, 25);
+    all.activities.get_mut(&tennis).unwrap().removed = removed;
+    // a current activity took the name up
+    all.insert(
+        String::from("PLAY TENNIS"),
+        ActivityDetails::new_activity(FrequencyType::from_code("WE")),
+    );
+    let text = Document::from_activities(&all)
+        .render(Format::Json)
+        .unwrap();
+    assert!(text.contains("\"removed\": \"2024-01-25\""));
+    let imported = Document::parse(&text, Format::Json)
+        .unwrap()
+        .to_activities()
+        .unwrap();
 
-    #[test]
-    fn test_enter_non_ascii_char_inserts_new_char_at_cursor_position() {
-        let mut app = App::default();
-        app.input = String::from("Veräderung");
-        app.cursor_position = 5;
+    // restoring the backup changes nothing, the removed activity keeping its id
+    let replace = interchange::plan_import(&all, &imported, ImportMode::Replace);
+    assert!(replace.changes.is_empty(), "{:?}", replace.changes);
+    assert_eq!(replace.activities, all);
 
-        app.enter_char('N');
+    // a replace brings it back into another tracker, a merge leaves it out
+    let empty = AllActivities::new();
+    let replace = interchange::plan_import(&empty, &imported, ImportMode::Replace);
+    assert!(replace.changes.contains(&String::from(
+        "+ PLAY TENNIS: removed on 2024-01-25 with 0 completions"
+    )));
+    let restored = replace
+        .activities
+        .activities
+        .values()
+        .find(|a| a.is_removed());
+    assert_eq!(restored, Some(&all.activities[&tennis]));
+    let merge = interchange::plan_import(&empty, &imported, ImportMode::Merge);
+    assert_eq!(merge.activities.activities.len(), all.current().count());
+    assert!(merge
+        .activities
+        .activities
+        .values()
+        .all(|a| !a.is_removed()));
 
-        // Ensure that 'X' is inserted at cursor position
-        assert_eq!(app.input, "VeräNderung");
-        // Ensure that cursor position is moved to the right
-        assert_eq!(app.cursor_position, 6);
+    // a replace from a file without removed activities, like a calendar, keeps them
+    let mut current_only = imported.clone();
+    current_only
+        .activities
+        .retain(|_, activity| !activity.is_removed());
+    let replace = interchange::plan_import(&all, &current_only, ImportMode::Replace);
+    assert_eq!(
+        replace.activities.activities[&tennis],
+        all.activities[&tennis]
+    );
+}
+
+#[test]
+fn test_import_rejects_invalid_documents() {
+    let invalid = [
+        r#"{"version": 1, "activities": [{"name": "A", "start": "2024-01-01T00:00:00Z", "frequency": "XX"}]}"#,
+        r#"{"version": 1, "activities": [
+            {"name": "a", "start": "2024-01-01T00:00:00Z", "frequency": "AW"},
+            {"name": "A", "start": "2024-01-01T00:00:00Z", "frequency": "AW"}]}"#,
+    ];
+    for text in invalid {
+        let document = Document::parse(text, Format::Json).unwrap();
+        assert!(document.to_activities().is_err());
     }
+    assert!(Document::parse(r#"{"version": 4, "activities": []}"#, Format::Json).is_err());
+}
+
+#[test]
+fn test_csv_rows_follow_schedule_and_completions() {
+    use crate::csv_export;
+    use chrono::NaiveDate;
+
+    let all = fixture_activities();
+    let date = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+    let rows = csv_export::rows(&all, date(23));
+    let on = |d, name: &str| {
+        rows.iter()
+            .find(|row| row.date == date(d) && row.activity == name)
+    };
+
+    // 2024-01-20 is a Saturday
+    assert!(on(20, "READ").is_none());
+    assert!(on(20, "PLAY TENNIS").is_some_and(|row| row.scheduled && !row.done));
+    assert!(on(22, "PLAY TENNIS").is_none());
+    assert!(on(23, "READ").is_some_and(|row| row.scheduled && !row.done));
+    assert_eq!(rows.last().unwrap().date, date(23));
+
+    let run = on(20, "GO FOR A 2 KM RUN").unwrap();
+    assert!(run.done);
+    assert_eq!(run.frequency, "AW");
+    assert_eq!(run.completion_time.len(), "2024-01-20 17:31:57".len());
+    // done outside of the schedule on a Sunday
+    assert!(on(21, "READ").is_some_and(|row| !row.scheduled && row.done));
+
+    let mut csv = Vec::new();
+    csv_export::write_csv(&all, &mut csv).unwrap();
+    assert!(String::from_utf8(csv)
+        .unwrap()
+        .starts_with("date,activity,frequency,scheduled,done,completion_time,skip_reason\n"));
+}
 
-    pub fn test_remove_added_activity(app: &mut App, activity_name: String) {
-        // Add a new activity
-        let activity_name = String::from(activity_name);
-        // Remove the activity
-        if let Some(index) = app
-            .todays_activities
+#[test]
+fn test_loop_export_import_reports_unsupported_habits() {
+    use crate::activities_manager::Target;
+    use crate::loop_import;
+    use chrono::NaiveDate;
+    use std::io::Write;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("Loop Habits CSV.zip");
+    let mut zip = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+    let mut add = |name: &str, text: &str| {
+        zip.start_file(name, zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(text.as_bytes()).unwrap();
+    };
+    add(
+        "Habits.csv",
+        "Position,Name,Type,Question,Description,FrequencyNumerator,FrequencyDenominator,Color,Unit,Target Type,Target Value,Archived?\n\
+         001,Meditate,YES_NO,,,1,1,#FF8F00,,AT_LEAST,0.0,false\n\
+         002,Gym,YES_NO,,,3,7,#AFB42B,,AT_LEAST,0.0,false\n\
+         003,Water,NUMERICAL,,,1,1,#1E88E5,cups,AT_LEAST,8.0,false\n\
+         004,Journal,YES_NO,,,1,1,#00897B,,AT_LEAST,0.0,true\n\
+         005,Stretch,YES_NO,,,2,3,#5E35B1,,AT_LEAST,0.0,false\n\
+         006,Coffee,NUMERICAL,,,1,1,#6D4C41,cups,AT_MOST,2.0,false\n\
+         007,Run,NUMERICAL,,,1,7,#E53935,km,AT_LEAST,20.0,false\n",
+    );
+    add(
+        "001 Meditate/Checkmarks.csv",
+        "2024-01-23,3\n2024-01-22,2\n2024-01-21,0\n2024-01-20,2\n2024-01-19,-1\n",
+    );
+    add("002 Gym/Checkmarks.csv", "2024-01-22,2\n");
+    add(
+        "003 Water/Checkmarks.csv",
+        "2024-01-22,8000\n2024-01-21,3500\n2024-01-20,0\n",
+    );
+    zip.finish().unwrap();
+
+    let import = loop_import::read_export(&path).unwrap();
+    let mut names: Vec<_> = import
+        .activities
+        .activities
+        .values()
+        .map(|activity| activity.name.as_str())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["GYM", "MEDITATE", "WATER"]);
+    assert_eq!(
+        import.activities.get("GYM").unwrap().frequency,
+        FrequencyType::from_code("3/W")
+    );
+    let meditate = import.activities.get("MEDITATE").unwrap();
+    assert_eq!(meditate.frequency, FrequencyType::AllWeek);
+    let days: std::collections::BTreeSet<_> = meditate
+        .completion_timestamps
+        .iter()
+        .map(|timestamp| crate::time_utils::local_date(*timestamp).to_string())
+        .collect();
+    assert_eq!(
+        days.into_iter().collect::<Vec<_>>(),
+        ["2024-01-20", "2024-01-22"]
+    );
+    assert_eq!(
+        crate::time_utils::local_date(meditate.start).to_string(),
+        "2024-01-19"
+    );
+    // skips have no reason in Loop
+    let skipped = NaiveDate::from_ymd_opt(2024, 1, 23).unwrap();
+    assert_eq!(
+        meditate.skips,
+        [(skipped, String::from("Skipped in Loop"))].into()
+    );
+    // numerical habits log their amounts, done once they reach the target
+    let water = import.activities.get("WATER").unwrap();
+    assert_eq!(water.target, Target::from_code("8 cups"));
+    let day = |day| NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+    assert_eq!(water.progress_on(day(22)), 8.0);
+    assert_eq!(water.progress_on(day(21)), 3.5);
+    assert_eq!(water.progress.len(), 2);
+    assert!(water.is_done_on(day(22)));
+    assert!(!water.is_done_on(day(21)));
+    assert_eq!(
+        import.report,
+        [
+            "JOURNAL: skipped, archived",
+            "STRETCH: skipped, 2 times every 3 days has no matching frequency",
+            "COFFEE: skipped, numerical habits with an at most target are not supported",
+            "RUN: skipped, targets over 7 days are not supported",
+        ]
+    );
+
+    // older exports name the frequency columns differently
+    let files = [(
+        String::from("Habits.csv"),
+        String::from(
+            "Position,Name,Description,NumRepetitions,Interval,Color\n001,Read,,7,7,#000000\n",
+        ),
+    )]
+    .into_iter()
+    .collect();
+    let import = loop_import::parse_export(&files).unwrap();
+    assert_eq!(
+        import.activities.get("READ").unwrap().frequency,
+        FrequencyType::AllWeek
+    );
+    assert_eq!(
+        import.report,
+        ["READ: no Checkmarks.csv, imported without history"]
+    );
+}
+
+#[test]
+fn test_ics_export_recurs_and_marks_completions() {
+    use crate::ics_export;
+    use chrono::DateTime;
+
+    let now = DateTime::from_timestamp(1706000000, 0).unwrap();
+    let ics = ics_export::calendar(&fixture_activities(), "c0ffee", now);
+    let unfolded = ics.replace("\r\n ", "");
+    let lines: Vec<&str> = unfolded.split("\r\n").collect();
+    assert_eq!(lines.first(), Some(&"BEGIN:VCALENDAR"));
+    assert_eq!(
+        lines.iter().filter(|line| **line == "BEGIN:VEVENT").count(),
+        3 + 3
+    );
+    assert!(lines.contains(&"RRULE:FREQ=DAILY"));
+    assert!(lines.contains(&"RRULE:FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR"));
+    assert!(lines.contains(&"RRULE:FREQ=WEEKLY;BYDAY=SA,SU"));
+    assert!(lines.contains(&"UID:activity-1-c0ffee@yacht"));
+    assert!(lines.contains(&"DTSTAMP:20240123T085320Z"));
+    assert!(lines.contains(&"SUMMARY:✓ GO FOR A 2 KM RUN"));
+    // READ was done on a Sunday, outside of its working days schedule
+    assert!(lines.contains(&"UID:activity-3-c0ffee-20240121@yacht"));
+    // series start on their first occurrence, READ having started on that Sunday
+    let series_start = |uid: &str| {
+        let at = lines.iter().position(|line| *line == uid).unwrap();
+        lines[at + 2]
+    };
+    assert_eq!(
+        series_start("UID:activity-3-c0ffee@yacht"),
+        "DTSTART;VALUE=DATE:20240122"
+    );
+    assert_eq!(
+        series_start("UID:activity-2-c0ffee@yacht"),
+        "DTSTART;VALUE=DATE:20240120"
+    );
+    assert!(ics.split("\r\n").all(|line| line.len() <= 75));
+    assert!(ics.ends_with("END:VCALENDAR\r\n"));
+
+    // renaming keeps the series and its overrides
+    let mut renamed = fixture_activities();
+    renamed.get_mut("READ").unwrap().name = String::from("READ A BOOK");
+    let ics = ics_export::calendar(&renamed, "c0ffee", now);
+    assert!(ics.contains("UID:activity-3-c0ffee@yacht\r\n"));
+    assert!(ics.contains("UID:activity-3-c0ffee-20240121@yacht\r\n"));
+    assert!(ics.contains("SUMMARY:READ A BOOK\r\n"));
+}
+
+#[test]
+fn test_weekday_set_frequency() {
+    use crate::activities_manager::WeekdaySet;
+    use crate::ics_export;
+    use chrono::Weekday;
+
+    let gym = FrequencyType::from_code(" fri,mon wednesday");
+    let set = WeekdaySet::new([Weekday::Mon, Weekday::Wed, Weekday::Fri]);
+    assert_eq!(gym, FrequencyType::Weekdays(set));
+    assert_eq!(gym.code(), "MON,WED,FRI");
+    assert_eq!(FrequencyType::from_code(&gym.code()), gym);
+    assert_eq!(FrequencyType::from_code("we"), FrequencyType::WeekEnds);
+    assert_eq!(
+        FrequencyType::from_code("wed"),
+        FrequencyType::Weekdays(WeekdaySet::new([Weekday::Wed]))
+    );
+    assert_eq!(
+        FrequencyType::from_code("mon,someday"),
+        FrequencyType::Invalid
+    );
+    assert_eq!(FrequencyType::from_code(","), FrequencyType::Invalid);
+
+    assert!(gym.is_scheduled_on(Weekday::Wed));
+    assert!(!gym.is_scheduled_on(Weekday::Thu));
+    assert!(FrequencyType::WorkingDays.is_scheduled_on(Weekday::Fri));
+    assert!(!FrequencyType::WeekEnds.is_scheduled_on(Weekday::Fri));
+    assert_eq!(
+        ics_export::rrule(&gym).as_deref(),
+        Some("FREQ=WEEKLY;BYDAY=MO,WE,FR")
+    );
+
+    let mut app = App {
+        new_activity_name: String::from("GYM"),
+        input: String::from("MON,WED,FRI"),
+        ..App::default()
+    };
+    app.convert_activity_frequency();
+    app.add_new_activity();
+    assert_eq!(app.all_activities.get("GYM").unwrap().frequency, gym);
+    let today = chrono::Datelike::weekday(&crate::time_utils::current_date());
+    assert_eq!(
+        app.todays_activities.contains(&String::from("GYM")),
+        set.contains(today)
+    );
+
+    let empty = ActivityDetails::new_activity(FrequencyType::Weekdays(WeekdaySet::default()));
+    assert!(app
+        .all_activities
+        .add_activity(app.storage.as_mut(), String::from("NEVER"), empty)
+        .is_err());
+}
+
+#[test]
+fn test_quota_is_due_until_met() {
+    use crate::activities_manager::{Day, QuotaPeriod};
+    use chrono::{Local, NaiveDate, TimeZone};
+
+    let swim = FrequencyType::from_code(" 2/week");
+    assert_eq!(
+        swim,
+        FrequencyType::Quota {
+            times: 2,
+            period: QuotaPeriod::Week
+        }
+    );
+    assert_eq!(swim.code(), "2/W");
+    assert_eq!(FrequencyType::from_code("2/m").code(), "2/M");
+    assert_eq!(FrequencyType::from_code("0/W"), FrequencyType::Invalid);
+    assert_eq!(FrequencyType::from_code("8/W"), FrequencyType::Invalid);
+    assert_eq!(FrequencyType::from_code("3/Y"), FrequencyType::Invalid);
+
+    let date = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+    let noon = |d| {
+        Local
+            .from_local_datetime(&date(d).and_hms_opt(12, 0, 0).unwrap())
+            .unwrap()
+            .timestamp()
+    };
+    assert_eq!(QuotaPeriod::Week.start_of(date(24)), date(22));
+    assert_eq!(QuotaPeriod::Month.start_of(date(24)), date(1));
+
+    // 2024-01-22 is a Monday, done on Monday and Wednesday
+    let mut activity = ActivityDetails::new_activity(swim);
+    activity.completion_timestamps = [noon(22), noon(24)].into_iter().collect();
+    assert!(activity.is_due_on(date(22)));
+    assert!(activity.is_due_on(date(23)));
+    assert!(activity.is_due_on(date(24)));
+    assert!(!activity.is_due_on(date(25)));
+    assert!(!activity.is_due_on(date(28)));
+    // a new week starts over
+    assert!(activity.is_due_on(date(29)));
+
+    // a met quota counts as satisfied for the perfect day
+    let now = crate::time_utils::current_time_timestamp();
+    let mut all = AllActivities::new();
+    let mut met = ActivityDetails::new_activity(FrequencyType::from_code("1/M"));
+    met.completion_timestamps.insert(now);
+    all.insert(String::from("SWIM"), met);
+    assert!(Day::today(&all).is_perfect_day());
+    all.insert(
+        String::from("CALL MOM"),
+        ActivityDetails::new_activity(FrequencyType::from_code("1/M")),
+    );
+    assert!(!Day::today(&all).is_perfect_day());
+}
+
+#[test]
+fn test_interval_frequencies() {
+    use crate::activities_manager::IntervalAnchor;
+    use crate::ics_export;
+    use chrono::{Local, NaiveDate, TimeZone};
+
+    assert_eq!(
+        FrequencyType::from_code("3d"),
+        FrequencyType::Interval {
+            days: 3,
+            anchor: IntervalAnchor::Start
+        }
+    );
+    assert_eq!(FrequencyType::from_code("10D/last").code(), "10D/L");
+    assert_eq!(FrequencyType::from_code("0D"), FrequencyType::Invalid);
+    assert_eq!(FrequencyType::from_code("3D/W"), FrequencyType::Invalid);
+    assert_eq!(
+        ics_export::rrule(&FrequencyType::from_code("3D")).as_deref(),
+        Some("FREQ=DAILY;INTERVAL=3")
+    );
+    assert_eq!(ics_export::rrule(&FrequencyType::from_code("3D/L")), None);
+
+    let date = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+    let noon = |d| {
+        Local
+            .from_local_datetime(&date(d).and_hms_opt(12, 0, 0).unwrap())
+            .unwrap()
+            .timestamp()
+    };
+    let mut water = ActivityDetails::new_activity(FrequencyType::from_code("3D"));
+    water.start = noon(10);
+    let due: Vec<u32> = (8..=17).filter(|d| water.is_due_on(date(*d))).collect();
+    assert_eq!(due, [10, 13, 16]);
+
+    // only completions on due days override an occurrence of the series
+    water.completion_timestamps.extend([noon(13), noon(14)]);
+    let mut all = AllActivities::new();
+    all.insert(String::from("WATER"), water);
+    let now = chrono::DateTime::from_timestamp(1706000000, 0).unwrap();
+    let ics = ics_export::calendar(&all, "c0ffee", now);
+    let event = |uid: &str| {
+        let at = ics.find(&format!("UID:{}@yacht", uid)).unwrap();
+        ics[at..at + ics[at..].find("END:VEVENT").unwrap()].to_string()
+    };
+    assert!(event("activity-1-c0ffee-20240114").contains("DTSTART;VALUE=DATE:20240114"));
+    assert!(!event("activity-1-c0ffee-20240114").contains("RECURRENCE-ID"));
+    assert!(ics.contains("RECURRENCE-ID;VALUE=DATE:20240113"));
+    assert!(!ics.contains("UID:activity-1-c0ffee-20240113@yacht"));
+
+    // rolls forward from the last completion and stays due until done
+    let mut contacts = ActivityDetails::new_activity(FrequencyType::from_code("3D/L"));
+    contacts.start = noon(10);
+    assert!(!contacts.is_due_on(date(9)));
+    assert!(contacts.is_due_on(date(10)));
+    assert!(contacts.is_due_on(date(11)));
+    contacts.completion_timestamps.insert(noon(11));
+    let due: Vec<u32> = (11..=16).filter(|d| contacts.is_due_on(date(*d))).collect();
+    assert_eq!(due, [11, 14, 15, 16]);
+    contacts.completion_timestamps.insert(noon(15));
+    let due: Vec<u32> = (14..=19).filter(|d| contacts.is_due_on(date(*d))).collect();
+    assert_eq!(due, [14, 15, 18, 19]);
+}
+
+#[test]
+fn test_monthly_rules() {
+    use crate::activities_manager::{Day, MonthlyRule};
+    use crate::ics_export;
+    use chrono::{DateTime, Datelike, Duration, NaiveDate, Weekday};
+
+    let last_sunday = FrequencyType::from_code("last sunday");
+    assert_eq!(
+        last_sunday,
+        FrequencyType::Monthly(MonthlyRule::LastWeekday(Weekday::Sun))
+    );
+    assert_eq!(last_sunday.code(), "LAST SUN");
+    for code in [
+        "1ST", "2ND", "3RD", "11TH", "22ND", "31ST", "2ND TUE", "5TH FRI",
+    ] {
+        assert_eq!(FrequencyType::from_code(code).code(), code);
+    }
+    for code in [
+        "32ND",
+        "0TH",
+        "1TH",
+        "2ST",
+        "6TH MON",
+        "LAST",
+        "1ST MON TUE",
+    ] {
+        assert_eq!(
+            FrequencyType::from_code(code),
+            FrequencyType::Invalid,
+            "{}",
+            code
+        );
+    }
+
+    let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+    let due = |code: &str, y, m| -> Vec<u32> {
+        let rule = match FrequencyType::from_code(code) {
+            FrequencyType::Monthly(rule) => rule,
+            other => panic!("{:?}", other),
+        };
+        let first = date(y, m, 1);
+        (0..first.num_days_in_month() as i64)
+            .map(|n| first + Duration::days(n))
+            .filter(|day| rule.matches(*day))
+            .map(|day| day.day())
+            .collect()
+    };
+    assert_eq!(due("1ST", 2024, 2), [1]);
+    // the 31st falls on the last day of shorter months, leap years included
+    assert_eq!(due("31ST", 2024, 1), [31]);
+    assert_eq!(due("31ST", 2024, 4), [30]);
+    assert_eq!(due("31ST", 2024, 2), [29]);
+    assert_eq!(due("30TH", 2023, 2), [28]);
+    assert_eq!(due("29TH", 2024, 3), [29]);
+    // 2024-03-31 is a Sunday, and March 2024 has five Fridays but February only four
+    assert_eq!(due("LAST SUN", 2024, 3), [31]);
+    assert_eq!(due("LAST SUN", 2024, 2), [25]);
+    assert_eq!(due("2ND TUE", 2024, 1), [9]);
+    assert_eq!(due("5TH FRI", 2024, 3), [29]);
+    assert!(due("5TH FRI", 2024, 2).is_empty());
+
+    // a monthly series started mid-month starts on the next matching day
+    let mut budget = AllActivities::new();
+    let mut first = ActivityDetails::new_activity(FrequencyType::from_code("1ST"));
+    first.start = 1705708800;
+    budget.insert(String::from("BUDGET"), first);
+    let now = DateTime::from_timestamp(1706000000, 0).unwrap();
+    assert!(
+        ics_export::calendar(&budget, "c0ffee", now).contains("DTSTART;VALUE=DATE:20240201\r\n")
+    );
+    let id = budget.id_of("BUDGET").unwrap();
+    let noon = |date: NaiveDate| crate::time_utils::noon_timestamp(date);
+    let off_day = noon(NaiveDate::from_ymd_opt(2024, 2, 2).unwrap());
+    let budget_day = noon(NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+    let activity = budget.activities.get_mut(&id).unwrap();
+    activity.completion_timestamps.extend([off_day, budget_day]);
+    let ics = ics_export::calendar(&budget, "c0ffee", now);
+    assert!(ics.contains("UID:activity-1-c0ffee-20240202@yacht"));
+    assert!(!ics.contains("RECURRENCE-ID;VALUE=DATE:20240202"));
+    assert!(ics.contains("RECURRENCE-ID;VALUE=DATE:20240301"));
+    assert_eq!(
+        ics_export::rrule(&FrequencyType::from_code("30TH")).as_deref(),
+        Some("FREQ=MONTHLY;BYMONTHDAY=28,29,30;BYSETPOS=-1")
+    );
+    assert_eq!(
+        ics_export::rrule(&FrequencyType::from_code("2ND TUE")).as_deref(),
+        Some("FREQ=MONTHLY;BYDAY=2TU")
+    );
+    assert_eq!(
+        ics_export::rrule(&last_sunday).as_deref(),
+        Some("FREQ=MONTHLY;BYDAY=-1SU")
+    );
+
+    // Day::get_day looks at the date of any offset since the activity started
+    let mut all = AllActivities::new();
+    let mut budget = ActivityDetails::new_activity(FrequencyType::from_code("1ST"));
+    budget.start -= 31 * 24 * 3600;
+    all.insert(String::from("BUDGET REVIEW"), budget);
+    let today = crate::time_utils::current_date();
+    let next_first = (1..=31)
+        .find(|n| (today + Duration::days(*n)).day() == 1)
+        .unwrap();
+    assert!(!Day::get_day(&all, next_first)
+        .today_activities_list()
+        .0
+        .is_empty());
+    assert!(Day::get_day(&all, next_first + 1)
+        .today_activities_list()
+        .0
+        .is_empty());
+    let this_first = 1 - today.day() as i64;
+    assert!(!Day::get_day(&all, this_first)
+        .today_activities_list()
+        .0
+        .is_empty());
+}
+
+#[test]
+fn test_rrule_frequencies() {
+    use crate::rrule::Rrule;
+    use chrono::{Datelike, NaiveDate};
+
+    let biweekly = FrequencyType::from_code("rrule:freq=weekly;interval=2;byday=tu");
+    assert_eq!(
+        biweekly,
+        FrequencyType::Rrule(String::from("FREQ=WEEKLY;INTERVAL=2;BYDAY=TU"))
+    );
+    assert_eq!(biweekly.code(), "RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=TU");
+    for rule in [
+        "FREQ=HOURLY",
+        "INTERVAL=2",
+        "FREQ=DAILY;BYHOUR=9",
+        "FREQ=WEEKLY;BYDAY=2TU",
+        "FREQ=MONTHLY;BYMONTHDAY=32",
+        "FREQ=DAILY;COUNT=3;UNTIL=20240301",
+    ] {
+        assert!(rule.parse::<Rrule>().is_err(), "{}", rule);
+        assert_eq!(
+            FrequencyType::from_code(&format!("RRULE:{}", rule)),
+            FrequencyType::Invalid
+        );
+    }
+
+    let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+    // Saturday 2024-01-20
+    let start = date(2024, 1, 20);
+    let days = |rule: &str, last: NaiveDate| -> Vec<String> {
+        let rule: Rrule = rule.parse().unwrap();
+        start
+            .iter_days()
+            .take_while(|day| *day <= last)
+            .filter(|day| rule.occurs_on(start, *day))
+            .map(|day| format!("{}-{}", day.month(), day.day()))
+            .collect()
+    };
+    assert_eq!(
+        days("FREQ=WEEKLY;INTERVAL=2;BYDAY=TU,SA", date(2024, 2, 20)),
+        ["1-20", "1-30", "2-3", "2-13", "2-17"]
+    );
+    assert_eq!(
+        days("FREQ=DAILY;INTERVAL=10;UNTIL=20240210", date(2024, 3, 1)),
+        ["1-20", "1-30", "2-9"]
+    );
+    assert_eq!(
+        days("FREQ=MONTHLY;BYMONTHDAY=1,-1;COUNT=3", date(2024, 6, 1)),
+        ["1-31", "2-1", "2-29"]
+    );
+    assert_eq!(
+        days(
+            "FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1",
+            date(2024, 4, 30)
+        ),
+        ["1-31", "2-29", "3-29", "4-30"]
+    );
+    assert_eq!(
+        days("FREQ=YEARLY;BYMONTH=2;BYDAY=1MO", date(2026, 12, 31)),
+        ["2-5", "2-3", "2-2"]
+    );
+    assert_eq!(
+        days("FREQ=YEARLY", date(2026, 12, 31)),
+        ["1-20", "1-20", "1-20"]
+    );
+
+    let mut clean = ActivityDetails::new_activity(biweekly);
+    clean.start = 1705752000;
+    let due: Vec<u32> = (20..=31)
+        .filter(|d| clean.is_due_on(date(2024, 1, *d)))
+        .collect();
+    assert_eq!(due, [30]);
+}
+
+#[test]
+fn test_ics_import_round_trips_export() {
+    use crate::ics_export;
+    use crate::ics_import;
+    use chrono::DateTime;
+
+    let all = file_format::decode(include_bytes!("../tests/fixtures/activities_v6.bin")).unwrap();
+    let now = DateTime::from_timestamp(1706500000, 0).unwrap();
+    let import = ics_import::parse_calendar(&ics_export::calendar(&all, "c0ffee", now)).unwrap();
+    // quotas have no fixed days, so only their completions are in the calendar
+    assert_eq!(
+        import.report,
+        ["SWIM: completion on 2024-01-21 skipped, no recurring event with that name"]
+    );
+    assert_eq!(import.activities.activities.len(), all.activities.len() - 1);
+    for activity in import.activities.activities.values() {
+        let name = &activity.name;
+        let original = all.get(name).unwrap();
+        assert_eq!(activity.frequency, original.frequency, "{}", name);
+        assert_eq!(
+            activity.completion_timestamps, original.completion_timestamps,
+            "{}",
+            name
+        );
+    }
+
+    let calendar = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nSUMMARY:Stand\\, up\r\n\
+        DTSTART;TZID=Europe/Paris:20240122T090000\r\nRRULE:FREQ=WEEKLY;BYDAY=MO,TU,WE,T\r\n \
+        H,FR;UNTIL=20240301T000000Z\r\nEND:VEVENT\r\nBEGIN:VEVENT\r\nSUMMARY:Dentist\r\n\
+        DTSTART:20240125T100000\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+    let import = ics_import::parse_calendar(calendar).unwrap();
+    assert_eq!(
+        import.activities.get("STAND, UP").unwrap().frequency,
+        FrequencyType::Rrule(String::from(
+            "FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR;UNTIL=20240301T000000Z"
+        ))
+    );
+    assert_eq!(import.report, ["DENTIST: skipped, not a recurring event"]);
+}
+
+#[test]
+fn test_quantitative_activities_track_progress() {
+    use crate::activities_manager::{Day, Target};
+
+    let target = Target::from_code("2KM").unwrap();
+    assert_eq!(target.amount, 2.0);
+    assert_eq!(target.unit, "km");
+    assert_eq!(target.code(), "2 km");
+    assert_eq!(target.progress(Some(1.2)), "1.2/2 km");
+    assert_eq!(
+        Target::from_code("8").unwrap().progress(Some(1.0 / 3.0)),
+        "0.33/8"
+    );
+    for code in ["", "km", "0 km", "-1 km", "1.2.3 km"] {
+        assert_eq!(Target::from_code(code), None, "{}", code);
+    }
+
+    let mut storage = MemoryStorage::new();
+    let mut all = AllActivities::new();
+    all.add_activity(
+        &mut storage,
+        String::from("DRINK WATER"),
+        ActivityDetails::new_activity(FrequencyType::AllWeek).with_target(Some(target)),
+    )
+    .unwrap();
+    all.add_activity(
+        &mut storage,
+        String::from("READ"),
+        ActivityDetails::new_activity(FrequencyType::AllWeek),
+    )
+    .unwrap();
+    assert_eq!(
+        all.log_progress(&mut storage, String::from("READ"), 1.0),
+        Err("Activity Has No Target")
+    );
+    assert_eq!(
+        all.log_progress(&mut storage, String::from("DRINK WATER"), 0.0),
+        Err("Invalid Amount")
+    );
+    // decimal amounts add up to the target despite rounding
+    for _ in 0..3 {
+        all.log_progress(&mut storage, String::from("DRINK WATER"), 0.4)
+            .unwrap();
+    }
+    let (names, statuses) = Day::today(&all).today_activities_list();
+    assert_eq!(names, ["DRINK WATER", "READ"]);
+    assert_eq!(statuses, ["1.2/2 km", "[-]"]);
+    assert_eq!(
+        all.log_progress(&mut storage, String::from("DRINK WATER"), 0.7),
+        Ok(false)
+    );
+    assert_eq!(
+        all.log_progress(&mut storage, String::from("DRINK WATER"), 0.1),
+        Ok(true)
+    );
+    let (_, statuses) = Day::today(&all).today_activities_list();
+    assert_eq!(statuses, ["[+]", "[-]"]);
+    assert_eq!(storage.load().unwrap().value, all);
+
+    for format in [Format::Json, Format::Toml] {
+        let text = Document::from_activities(&all).render(format).unwrap();
+        let imported = Document::parse(&text, format)
+            .unwrap()
+            .to_activities()
+            .unwrap();
+        assert_eq!(imported.activities, all.activities);
+    }
+
+    let mut app = App {
+        all_activities: all,
+        storage: Box::new(storage),
+        ..App::default()
+    };
+    app.all_activities
+        .get_mut("DRINK WATER")
+        .unwrap()
+        .completion_timestamps
+        .clear();
+    (app.todays_activities, app.activities_status) =
+        Day::today(&app.all_activities).today_activities_list();
+    app.set_activity_done(0);
+    assert_eq!(app.input_mode, InputMode::ActiveProgress);
+    assert_eq!(app.logs, "How many km of DRINK WATER were done?");
+    app.input = String::from("2");
+    app.log_progress();
+    assert_eq!(app.logs, "Target reached, activity is set done!");
+}
+
+#[test]
+fn test_activities_to_avoid_are_done_until_a_slip() {
+    use crate::activities_manager::{ActivityKind, Day, Target};
+    use crate::{csv_export, ics_export, ics_import, time_utils};
+    use chrono::Utc;
+
+    let mut storage = MemoryStorage::new();
+    let mut all = AllActivities::new();
+    let avoid = |code: &str| {
+        ActivityDetails::new_activity(FrequencyType::from_code(code)).with_kind(ActivityKind::Avoid)
+    };
+    assert_eq!(
+        all.add_activity(&mut storage, String::from("NO SNACKS"), avoid("3/W")),
+        Err("Activities To Avoid Need Fixed Days")
+    );
+    assert_eq!(
+        all.add_activity(
+            &mut storage,
+            String::from("NO SNACKS"),
+            avoid("AW").with_target(Target::from_code("2"))
+        ),
+        Err("Activities To Avoid Have No Target")
+    );
+    all.add_activity(&mut storage, String::from("NO SUGAR"), avoid("AW"))
+        .unwrap();
+    let today = Day::today(&all);
+    assert!(today.is_perfect_day());
+    assert_eq!(today.today_activities_list().1, ["[+]"]);
+
+    all.set_activity_done(&mut storage, String::from("NO SUGAR"))
+        .unwrap();
+    assert_eq!(
+        all.set_activity_done(&mut storage, String::from("NO SUGAR")),
+        Err("Slip Already Logged!")
+    );
+    let today = Day::today(&all);
+    assert!(!today.is_perfect_day());
+    assert_eq!(today.today_activities_list().1, ["[x]"]);
+    let rows = csv_export::rows(&all, time_utils::current_date());
+    assert!(rows.iter().all(|row| !row.done));
+
+    let text = Document::from_activities(&all)
+        .render(Format::Toml)
+        .unwrap();
+    assert!(text.contains("kind = \"avoid\""));
+    let imported = Document::parse(&text, Format::Toml)
+        .unwrap()
+        .to_activities()
+        .unwrap();
+    assert_eq!(imported.activities, all.activities);
+
+    let calendar = ics_export::calendar(&all, "c0ffee", Utc::now());
+    assert!(calendar.contains("SUMMARY:✗ NO SUGAR"));
+    let imported = ics_import::parse_calendar(&calendar).unwrap();
+    assert_eq!(
+        imported.activities.get("NO SUGAR").unwrap().kind,
+        ActivityKind::Avoid
+    );
+    assert_eq!(
+        imported
+            .activities
+            .get("NO SUGAR")
+            .unwrap()
+            .completion_timestamps,
+        all.get("NO SUGAR").unwrap().completion_timestamps
+    );
+}
+
+#[test]
+fn test_pauses_skip_days() {
+    use crate::activities_manager::{Day, Pause};
+    use crate::time_utils;
+    use chrono::Duration;
+
+    let mut app = App::default();
+    test_add_new_activity(&mut app, String::from("READ"));
+    test_add_new_activity(&mut app, String::from("RUN"));
+    let now = time_utils::current_time_timestamp();
+    for name in ["READ", "RUN"] {
+        let activity = app.all_activities.get_mut(name).unwrap();
+        activity.start = now - 40 * 86400;
+        activity
+            .completion_timestamps
+            .extend((1..=35).map(|days| now - days * 86400));
+    }
+    app.total_perfect_days(-30);
+    assert_eq!(app.total_perfect_days, 30);
+
+    // a paused day has nothing to do and the count looks further back instead
+    let today = time_utils::current_date();
+    let vacation = Pause {
+        from: today - Duration::days(10),
+        until: today - Duration::days(1),
+    };
+    app.all_activities
+        .pause(app.storage.as_mut(), None, vacation)
+        .unwrap();
+    let day = Day::get_day(&app.all_activities, -5);
+    assert!(day.is_paused());
+    assert!(!day.is_perfect_day());
+    assert_eq!(day.paused_activities(), ["READ", "RUN"]);
+    app.total_perfect_days(-30);
+    assert_eq!(app.total_perfect_days, 25);
+
+    // pausing one activity lists it apart
+    app.start_pause(Some(0));
+    assert_eq!(app.input_mode, InputMode::ActivePause);
+    app.input = (today + Duration::days(3)).to_string();
+    app.pause();
+    assert_eq!(app.logs, "Activity is paused!");
+    assert_eq!(app.todays_activities, ["RUN"]);
+    assert_eq!(app.paused_activities, ["READ"]);
+
+    app.start_pause(None);
+    app.input = String::from("soon");
+    app.pause();
+    assert!(app.logs.starts_with("Invalid dates"));
+
+    // resuming ends the pauses from today on and keeps the past ones
+    app.input.clear();
+    app.pause();
+    assert_eq!(app.logs, "Everything is resumed!");
+    assert_eq!(app.todays_activities, ["READ", "RUN"]);
+    assert!(app.paused_activities.is_empty());
+    assert_eq!(app.all_activities.pauses, [vacation]);
+    let loaded = app.storage.load().unwrap().value;
+    assert!(loaded.get("READ").unwrap().pauses.is_empty());
+    assert_eq!(loaded.pauses, [vacation]);
+}
+
+#[test]
+fn test_skipped_days_are_neutral() {
+    use crate::activities_manager::Day;
+    use crate::{csv_export, time_utils};
+
+    let mut storage = MemoryStorage::default();
+    let mut all = AllActivities::new();
+    for name in ["READ", "RUN"] {
+        all.add_activity(
+            &mut storage,
+            String::from(name),
+            ActivityDetails::new_activity(FrequencyType::AllWeek),
+        )
+        .unwrap();
+    }
+    let skip = |all: &mut AllActivities, storage: &mut MemoryStorage, name: &str, reason: &str| {
+        all.skip_activity(storage, String::from(name), String::from(reason))
+    };
+    assert_eq!(
+        skip(&mut all, &mut storage, "READ", " "),
+        Err("Reason Can't Be Empty")
+    );
+    all.set_activity_done(&mut storage, String::from("RUN"))
+        .unwrap();
+    assert_eq!(
+        skip(&mut all, &mut storage, "RUN", "RAIN"),
+        Err("Already Done!")
+    );
+
+    // a skipped activity doesn't keep the day from being perfect
+    skip(&mut all, &mut storage, "READ", "SICK").unwrap();
+    let today = Day::today(&all);
+    assert_eq!(today.remaining(), 0);
+    assert!(today.is_perfect_day());
+    assert!(!today.is_excused());
+    let (names, labels) = today.today_activities_list();
+    let read = names.iter().position(|name| name == "READ").unwrap();
+    assert_eq!(labels[read], "[~] SICK");
+    assert_eq!(storage.load().unwrap().value, all);
+
+    // a day with everything skipped is excused rather than perfect or missed
+    let mut sick = AllActivities::new();
+    let mut read = ActivityDetails::new_activity(FrequencyType::AllWeek);
+    read.skips
+        .insert(time_utils::current_date(), String::from("SICK"));
+    sick.insert(String::from("READ"), read);
+    let today = Day::today(&sick);
+    assert!(today.is_excused());
+    assert!(!today.is_perfect_day());
+
+    let rows = csv_export::rows(&all, time_utils::current_date());
+    let row = rows.iter().find(|row| row.activity == "READ").unwrap();
+    assert!(row.scheduled && !row.done);
+    assert_eq!(row.skip_reason, "SICK");
+}
+
+#[test]
+fn test_undo_and_redo_survive_a_restart() {
+    use crate::history::{self, History};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(ACTIVITIES_FILE);
+    let open = || {
+        App::new(Box::new(FileStorage::new(path.clone())))
+            .with_history(History::load(history::history_path(&path)))
+    };
+    let mut app = open();
+    test_add_new_activity(&mut app, String::from("THINK"));
+    test_add_new_activity(&mut app, String::from("READ"));
+    let read = app.all_activities.id_of("READ").unwrap();
+    let index = |app: &App, name: &str| {
+        app.todays_activities
             .iter()
-            .position(|x| *x == activity_name)
-        {
-            app.remove_activity(index);
-            assert!(!app.all_activities.is_in_activities(activity_name));
-            assert_eq!(app.logs, "Activity is removed!");
-        } else {
-            panic!("Activity not found in todays_activities");
-        }
-    }
-
-    pub fn test_add_new_activity(app: &mut App, name: String) {
-        let frequency = FrequencyType::AllWeek;
-
-        app.input = name.clone();
-        app.input_mode = InputMode::ActiveName;
-        app.add_new_activity_name();
-        app.new_activity_frequency = frequency;
-
-        app.add_new_activity();
-        //dbg!(app.new_activity_frequency);
-        //dbg!(app.new_activity_name);
-
-        // Ensure that activity is added to all_activities
-        assert!(app.all_activities.is_in_activities(name.clone()));
-        // Ensure that new_activity_name is cleared after adding activity
-        assert_eq!(app.new_activity_name, "");
-        // Ensure that new_activity_frequency is set correctly after adding activity
-        assert_eq!(app.new_activity_frequency, FrequencyType::Invalid);
-        // Ensure that logs indicate successful activity addition
-        assert_eq!(app.logs, "Activity is added!");
-        // Ensure that global status is refreshed after adding activity
-        assert_eq!(app.day_status, false); // As it's not a perfect day yet
-        assert_eq!(app.total_perfect_days, 0); // As it's not a perfect day yet
-    }
-
-    #[test]
-    fn test_add_new_activity_and_remove_ascii_and_not() {
-        let mut app = App::default();
-        test_add_new_activity(&mut app, String::from("THINK"));
-        test_remove_added_activity(&mut app, String::from("THINK"));
-        test_add_new_activity(&mut app, String::from("BÜCHER LESEN"));
-        test_remove_added_activity(&mut app, String::from("BÜCHER LESEN"));
+            .position(|activity| activity == name)
+            .unwrap()
+    };
+    app.set_activity_done(index(&app, "READ"));
+    test_remove_added_activity(&mut app, String::from("READ"));
+
+    // the removal is undone after a restart, with the history and the id
+    let mut app = open();
+    assert!(app.history.can_undo());
+    app.undo();
+    assert_eq!(app.logs, "Undone: READ is removed");
+    assert_eq!(app.all_activities.id_of("READ"), Some(read));
+    assert!(app
+        .all_activities
+        .get("READ")
+        .unwrap()
+        .is_done_on(app.current_day));
+    app.undo();
+    assert_eq!(app.logs, "Undone: READ is set done");
+    assert!(!app
+        .all_activities
+        .get("READ")
+        .unwrap()
+        .is_done_on(app.current_day));
+    assert_eq!(app.storage.load().unwrap().value, app.all_activities);
+
+    app.redo();
+    assert_eq!(app.logs, "Redone: READ is set done");
+    assert!(app
+        .all_activities
+        .get("READ")
+        .unwrap()
+        .is_done_on(app.current_day));
+    app.undo();
+    app.undo();
+    assert!(!app.all_activities.is_in_activities(String::from("READ")));
+    assert_eq!(app.storage.load().unwrap().value, app.all_activities);
+
+    // a new change drops what could be redone
+    app.set_activity_done(index(&app, "THINK"));
+    assert!(!app.history.can_redo());
+    app.redo();
+    assert_eq!(app.logs, "Error: Nothing To Redo");
+    app.undo();
+    app.undo();
+    assert!(app.all_activities.activities.is_empty());
+    app.undo();
+    assert_eq!(app.logs, "Error: Nothing To Undo");
+}
+
+#[test]
+fn test_progress_and_skips_are_undone() {
+    use crate::activities_manager::Target;
+
+    let mut app = App::default();
+    let water = ActivityDetails::new_activity(FrequencyType::AllWeek)
+        .with_target(Target::from_code("2 km"));
+    let storage = app.storage.as_mut();
+    app.all_activities
+        .add_activity(storage, String::from("DRINK WATER"), water)
+        .unwrap();
+    let storage = app.storage.as_mut();
+    app.all_activities
+        .add_activity(
+            storage,
+            String::from("READ"),
+            ActivityDetails::new_activity(FrequencyType::AllWeek),
+        )
+        .unwrap();
+    app.refresh_list();
+    let today = app.current_day;
+    let water = |app: &App| app.all_activities.get("DRINK WATER").unwrap().clone();
+
+    // the completion set by reaching the target goes with the progress
+    app.picked_activity = String::from("DRINK WATER");
+    app.input = String::from("1.5");
+    app.log_progress();
+    app.input = String::from("0.5");
+    app.log_progress();
+    assert!(water(&app).is_done_on(today));
+    app.undo();
+    assert_eq!(app.logs, "Undone: DRINK WATER progress is logged");
+    assert!(!water(&app).is_done_on(today));
+    assert_eq!(water(&app).progress_on(today), 1.5);
+    assert_eq!(app.storage.load().unwrap().value, app.all_activities);
+    app.redo();
+    assert!(water(&app).is_done_on(today));
+    app.undo();
+    app.undo();
+    assert!(water(&app).progress.is_empty());
+    assert_eq!(app.storage.load().unwrap().value, app.all_activities);
+
+    // undoing a new reason brings back the one it replaced
+    app.picked_activity = String::from("READ");
+    for reason in ["SICK", "TRAVEL"] {
+        app.input = String::from(reason);
+        app.skip_activity();
     }
+    let skip = |app: &App| {
+        app.all_activities
+            .get("READ")
+            .unwrap()
+            .skips
+            .get(&today)
+            .cloned()
+    };
+    app.undo();
+    assert_eq!(app.logs, "Undone: READ is skipped");
+    assert_eq!(skip(&app).as_deref(), Some("SICK"));
+    app.undo();
+    assert_eq!(skip(&app), None);
+    assert_eq!(app.storage.load().unwrap().value, app.all_activities);
+    app.redo();
+    app.redo();
+    assert_eq!(skip(&app).as_deref(), Some("TRAVEL"));
+    assert_eq!(app.storage.load().unwrap().value, app.all_activities);
+}
+
+#[test]
+fn test_backfill_past_days() {
+    let mut app = App::default();
+    test_add_new_activity(&mut app, String::from("READ"));
+    let yesterday = crate::time_utils::adjusted_date(-1);
+    let id = app.all_activities.id_of("READ").unwrap();
+    app.all_activities.activities.get_mut(&id).unwrap().start -= 24 * 3600;
+    app.storage.save(&app.all_activities).unwrap();
+
+    app.next_day();
+    assert_eq!(app.day_offset, 0);
+    assert_eq!(app.logs, "Days after today can't be logged yet");
+    app.previous_day();
+    assert_eq!(app.listed_date(), yesterday);
+    assert_eq!(app.activities_status, ["[-]"]);
+
+    // `d` toggles the completion of the day listed, today is left as it is
+    app.set_activity_done(0);
+    assert_eq!(app.logs, format!("READ is set done on {}", yesterday));
+    assert_eq!(app.activities_status, ["[+]"]);
+    let read = app.all_activities.get("READ").unwrap();
+    assert!(read.is_done_on(yesterday));
+    assert!(!read.is_done_on(app.current_day));
+    assert_eq!(app.storage.load().unwrap().value, app.all_activities);
+
+    app.set_activity_done(0);
+    assert_eq!(app.logs, format!("READ is set not done on {}", yesterday));
+    assert_eq!(app.activities_status, ["[-]"]);
+    app.undo();
+    assert_eq!(app.activities_status, ["[+]"]);
+    app.start_skip(0);
+    assert_ne!(app.input_mode, InputMode::ActiveSkip);
+
+    app.next_day();
+    assert_eq!(app.day_offset, 0);
+    assert_eq!(app.activities_status, ["[-]"]);
+    app.set_activity_done(0);
+    assert_eq!(app.logs, "Activity is set done!");
+}
+
+#[test]
+fn test_past_days_leave_the_activities_as_they_are() {
+    use crate::handler::handle_key_events;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    let mut app = App::default();
+    test_add_new_activity(&mut app, String::from("READ"));
+    let id = app.all_activities.id_of("READ").unwrap();
+    app.all_activities.activities.get_mut(&id).unwrap().start -= 24 * 3600;
+    app.storage.save(&app.all_activities).unwrap();
+    let before = app.all_activities.clone();
+    app.input_mode = InputMode::Inactive;
+
+    app.previous_day();
+    for (key, action) in [('r', "removed"), ('e', "edited"), ('p', "paused")] {
+        handle_key_events(
+            KeyEvent::new(KeyCode::Char(key), KeyModifiers::NONE),
+            &mut app,
+        )
+        .unwrap();
+        assert_eq!(app.input_mode, InputMode::Inactive);
+        assert_eq!(
+            app.logs,
+            format!("Only today can be {}, press `]` to go back to it", action)
+        );
+    }
+    assert_eq!(app.all_activities, before);
+    assert_eq!(app.storage.load().unwrap().value, before);
+
+    // the whole tracker can still be paused from any day
+    handle_key_events(
+        KeyEvent::new(KeyCode::Char('v'), KeyModifiers::NONE),
+        &mut app,
+    )
+    .unwrap();
+    assert_eq!(app.input_mode, InputMode::ActivePause);
+}
+
+#[test]
+fn test_completions_keep_their_local_day() {
+    use crate::time_utils::{self, day_of};
+    use chrono::{DateTime, NaiveDate};
+
+    // a late night counts for the day before when days start later
+    let late = DateTime::parse_from_rfc3339("2024-01-23T01:30:00+01:00").unwrap();
+    let date = |day| NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+    assert_eq!(day_of(&late, 0), date(23));
+    assert_eq!(day_of(&late, 4), date(22));
+    assert_eq!(time_utils::parse_day_start(" 4 "), Ok(4));
+    assert!(time_utils::parse_day_start("13").is_err());
+    assert!(time_utils::parse_day_start("late").is_err());
+    let args = |args: &[&str]| Cli::parse(args.iter().map(|arg| arg.to_string()));
+    assert_eq!(args(&["--day-start", "4"]).unwrap().day_start, Some(4));
+    assert!(args(&["--day-start=24"]).is_err());
+
+    // completions done now record the day and offset they were logged at
+    let mut storage = MemoryStorage::new();
+    let mut all = AllActivities::new();
+    all.add_activity(
+        &mut storage,
+        String::from("JOURNAL"),
+        ActivityDetails::new_activity(FrequencyType::AllWeek),
+    )
+    .unwrap();
+    let now = all
+        .set_activity_done(&mut storage, String::from("JOURNAL"))
+        .unwrap();
+    let journal = all.get("JOURNAL").unwrap();
+    assert_eq!(journal.completion_days[&now], LocalDay::of(now));
+    assert_eq!(storage.load().unwrap().value, all);
+
+    // the offset survives an export, whatever the time zone of the importing machine
+    let id = all.id_of("JOURNAL").unwrap();
+    let day = LocalDay {
+        date: date(23),
+        offset: 19800,
+    };
+    all.activities
+        .get_mut(&id)
+        .unwrap()
+        .add_completion(1705969800, day);
+    let text = Document::from_activities(&all)
+        .render(Format::Json)
+        .unwrap();
+    assert!(text.contains("2024-01-23T06:00:00+05:30"));
+    let imported = Document::parse(&text, Format::Json)
+        .unwrap()
+        .to_activities()
+        .unwrap();
+    let journal = imported.get("JOURNAL").unwrap();
+    assert_eq!(journal.completion_day(1705969800), day);
+    assert!(journal.is_done_on(date(23)));
+
+    // a late night logged under a later day start keeps its day through an export and an
+    // import under the default day start, as a document or as a calendar
+    let late = late.timestamp();
+    time_utils::set_day_start(4);
+    let day = LocalDay::of(late);
+    time_utils::set_day_start(0);
+    assert_eq!(day.date, date(22));
+    let mut all = AllActivities::new();
+    let mut sleep = ActivityDetails::new_activity(FrequencyType::AllWeek);
+    sleep.start = DateTime::parse_from_rfc3339("2024-01-01T12:00:00Z")
+        .unwrap()
+        .timestamp();
+    sleep.add_completion(late, day);
+    all.insert(String::from("SLEEP EARLY"), sleep);
+    let text = Document::from_activities(&all)
+        .render(Format::Toml)
+        .unwrap();
+    assert!(text.contains("day = \"2024-01-22\""));
+    let imported = Document::parse(&text, Format::Toml)
+        .unwrap()
+        .to_activities()
+        .unwrap();
+    assert_eq!(
+        imported.get("SLEEP EARLY").unwrap().completion_day(late),
+        day
+    );
+    let ics = crate::ics_export::calendar(&all, "c0ffee", chrono::Utc::now());
+    let imported = crate::ics_import::parse_calendar(&ics).unwrap().activities;
+    let sleep = imported.get("SLEEP EARLY").unwrap();
+    assert_eq!(sleep.completion_timestamps, [late].into());
+    assert_eq!(sleep.completion_day(late).date, date(22));
+
+    // progress keeps its day the same way
+    let mut storage = MemoryStorage::new();
+    let mut all = AllActivities::new();
+    let mut walk = ActivityDetails::new_activity(FrequencyType::AllWeek)
+        .with_target(crate::activities_manager::Target::from_code("2 km"));
+    walk.start = sleep.start;
+    all.add_activity(&mut storage, String::from("WALK"), walk)
+        .unwrap();
+    time_utils::set_day_start(4);
+    let done = all.log_progress_at(&mut storage, String::from("WALK"), 0.5, late);
+    time_utils::set_day_start(0);
+    assert_eq!(done, Ok(false));
+    let walk = all.get("WALK").unwrap();
+    assert_eq!(walk.progress_day(late), day);
+    assert_eq!(walk.progress_on(date(22)), 0.5);
+    assert_eq!(walk.progress_on(date(23)), 0.0);
+    assert_eq!(storage.load().unwrap().value, all);
+    let text = Document::from_activities(&all)
+        .render(Format::Json)
+        .unwrap();
+    let imported = Document::parse(&text, Format::Json)
+        .unwrap()
+        .to_activities()
+        .unwrap();
+    assert_eq!(imported.get("WALK").unwrap().progress_day(late), day);
+    // documents before version 3 count it for the local day of its time
+    let old = text
+        .replace("\"version\": 3", "\"version\": 2")
+        .replace(",\n          \"day\": \"2024-01-22\"", "");
+    assert!(!old.contains("2024-01-22"));
+    let imported = Document::parse(&old, Format::Json)
+        .unwrap()
+        .to_activities()
+        .unwrap();
+    assert_eq!(imported.get("WALK").unwrap().progress_on(date(23)), 0.5);
+}
+
+#[test]
+fn test_days_only_list_activities_existing_then() {
+    use crate::activities_manager::Day;
+    use crate::time_utils::{adjusted_date, current_date, noon_timestamp};
+
+    // READ started three days ago and was done every day since
+    let mut storage = MemoryStorage::new();
+    let mut all = AllActivities::new();
+    let mut read = ActivityDetails::new_activity(FrequencyType::AllWeek);
+    read.start = noon_timestamp(adjusted_date(-3));
+    all.add_activity(&mut storage, String::from("READ"), read)
+        .unwrap();
+    for adjustment in -3..0 {
+        all.set_activity_done_on(
+            &mut storage,
+            String::from("READ"),
+            adjusted_date(adjustment),
+        )
+        .unwrap();
+    }
+    all.set_activity_done(&mut storage, String::from("READ"))
+        .unwrap();
+    let mut app = App::new(Box::new(storage));
+    assert_eq!(app.total_perfect_days, 4);
+    assert!(Day::get_day(&app.all_activities, -4)
+        .today_activities_list()
+        .0
+        .is_empty());
+
+    // an activity added today leaves the days before as they were
+    app.all_activities
+        .add_activity(
+            app.storage.as_mut(),
+            String::from("THINK"),
+            ActivityDetails::new_activity(FrequencyType::AllWeek),
+        )
+        .unwrap();
+    app.refresh_global_status();
+    assert_eq!(app.total_perfect_days, 3);
+    let (listed, _) = Day::get_day(&app.all_activities, -1).today_activities_list();
+    assert_eq!(listed, ["READ"]);
+
+    // a removed one stays on the days before its removal, under a name free to use again
+    app.all_activities
+        .remove_activity(app.storage.as_mut(), String::from("READ"))
+        .unwrap();
+    app.refresh_global_status();
+    assert_eq!(app.total_perfect_days, 3);
+    let (listed, _) = Day::get_day(&app.all_activities, 0).today_activities_list();
+    assert_eq!(listed, ["THINK"]);
+    let (listed, statuses) = Day::get_day(&app.all_activities, -1).today_activities_list();
+    assert_eq!(
+        (listed, statuses),
+        (vec![String::from("READ")], vec![String::from("[+]")])
+    );
+    assert_eq!(app.all_activities.get("READ"), None);
+    let removed = app
+        .all_activities
+        .activities
+        .values()
+        .find(|a| a.is_removed());
+    assert_eq!(removed.unwrap().removed, Some(current_date()));
+    assert_eq!(app.storage.load().unwrap().value, app.all_activities);
+    assert!(app
+        .all_activities
+        .add_activity(
+            app.storage.as_mut(),
+            String::from("READ"),
+            ActivityDetails::new_activity(FrequencyType::AllWeek),
+        )
+        .is_ok());
+
+    // past days of a removed activity can't be changed
+    app.previous_day();
+    app.set_activity_done(0);
+    assert_eq!(app.logs, "Removed activities can't be changed");
+}
+
+#[test]
+fn test_long_statuses_end_with_an_ellipsis() {
+    assert_eq!(crate::ui::fit_status("[x] 3/3"), "[x] 3/3");
+    assert_eq!(crate::ui::fit_status("[~] 1234567890"), "[~] 1234567890");
+    assert_eq!(
+        crate::ui::fit_status("[~] sore back, rest"),
+        "[~] sore back…"
+    );
 }
@@ -1,4 +1,47 @@
-use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, Utc, Weekday};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use std::env;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Environment variable with the hour days start at, used when `--day-start` is not given.
+pub const DAY_START_ENV: &str = "YACHT_DAY_START";
+
+/// Latest hour a day can start at, so that noon is always part of the day.
+pub const MAX_DAY_START: u32 = 12;
+
+static DAY_START: AtomicU32 = AtomicU32::new(0);
+
+/// Hour from the `--day-start` flag, falling back to `YACHT_DAY_START` and then midnight.
+pub fn resolve_day_start(flag: Option<u32>) -> Result<u32, String> {
+    match (flag, env::var(DAY_START_ENV)) {
+        (Some(hour), _) => Ok(hour),
+        (None, Ok(value)) if !value.is_empty() => parse_day_start(&value),
+        (None, _) => Ok(0),
+    }
+}
+
+pub fn parse_day_start(value: &str) -> Result<u32, String> {
+    match value.trim().parse() {
+        Ok(hour) if hour <= MAX_DAY_START => Ok(hour),
+        _ => Err(format!(
+            "Invalid day start: {}. Please use an hour from 0 to {}.",
+            value, MAX_DAY_START
+        )),
+    }
+}
+
+/// Makes days start at `hour` o'clock: anything logged before that counts for the day before.
+pub fn set_day_start(hour: u32) {
+    DAY_START.store(hour.min(MAX_DAY_START), Ordering::Relaxed);
+}
+
+pub fn day_start() -> u32 {
+    DAY_START.load(Ordering::Relaxed)
+}
+
+/// Day that the local time of `datetime` counts for when days start at `day_start`.
+pub fn day_of<Tz: TimeZone>(datetime: &DateTime<Tz>, day_start: u32) -> NaiveDate {
+    (datetime.naive_local() - Duration::hours(i64::from(day_start))).date()
+}
 
 pub fn current_time_timestamp() -> i64 {
     Utc::now().timestamp()
@@ -9,22 +52,38 @@ pub fn current_time() -> DateTime<Local> {
 }
 
 pub fn current_date() -> NaiveDate {
-    current_time().date_naive()
+    day_of(&current_time(), day_start())
 }
 
-pub fn is_timestamp_on_day(timestamp: i64, adjustment: i64) -> bool {
-    let timestamp_date = NaiveDateTime::from_timestamp_opt(timestamp, 0)
-        .expect("Invalid timestamp")
-        .date();
-    let today_date = (Local::now() + Duration::days(adjustment)).date_naive();
-    timestamp_date == today_date
+/// Date `adjustment` days from today.
+pub fn adjusted_date(adjustment: i64) -> NaiveDate {
+    current_date() + Duration::days(adjustment)
 }
 
 pub fn todays_weekday(adjustment: i64) -> Weekday {
-    (current_time() + Duration::days(adjustment)).weekday()
+    adjusted_date(adjustment).weekday()
 }
 
 pub fn is_same_date(some_date: NaiveDate) -> bool {
-    let now_local = Local::now().date_naive();
-    some_date == now_local
+    some_date == current_date()
+}
+
+/// Timestamp of noon on the local `date`, for completions logged afterwards.
+pub fn noon_timestamp(date: NaiveDate) -> i64 {
+    let noon = NaiveTime::from_hms_opt(12, 0, 0).expect("Invalid time");
+    Local
+        .from_local_datetime(&date.and_time(noon))
+        .earliest()
+        .map_or_else(current_time_timestamp, |datetime| datetime.timestamp())
+}
+
+pub fn local_time(timestamp: i64) -> DateTime<Local> {
+    DateTime::from_timestamp(timestamp, 0)
+        .expect("Invalid timestamp")
+        .with_timezone(&Local)
+}
+
+/// Day a timestamp counts for in the local time zone.
+pub fn local_date(timestamp: i64) -> NaiveDate {
+    day_of(&local_time(timestamp), day_start())
 }
@@ -5,20 +5,29 @@ use ratatui::{
     Frame,
 };
 
+use crate::activities_manager::ActivityKind;
 use crate::app::{App, InputMode};
+/// Width of the status column, wide enough for progress like `1.2/2 km`.
+const STATUS_WIDTH: usize = 14;
+
+const FREQUENCY_HELP: &str = "AW all week, WD working days, WE weekends, days like MON,WED,FRI, quotas like 3/W, intervals like 3D, monthly like 1ST or LAST SUN, rules like RRULE:FREQ=WEEKLY;BYDAY=TU";
+
 /// Renders the user interface widgets.
 pub fn render(app: &mut App, frame: &mut Frame) {
     // This is where you add new widgets.
     // See the following resoursces:
     // - https://docs.rs/ratatui/latest/ratatui/widgets/index.html
     // - https://github.com/ratatui-org/ratatui/tree/master/examples
-    let p = Paragraph::new(format!(
+    let p = Paragraph::new(
         "COMMANDS:\n\
         Press `q` to stop running the program.\n\
-        Press `n` to add a new activity. `ESC` to quit new activity input mode.\n\
-        Select ACTIVITY + `d` to set an activity as DONE or `r` to REMOVE an activity \n\
+        Press `n` to add a new activity, or `a` to add one to AVOID. `ESC` to quit new activity input mode.\n\
+        Select ACTIVITY + `d` to set an activity as DONE (log an amount towards its target, or a slip of one to avoid) or `r` to REMOVE an activity \n\
+        Select ACTIVITY + `e` to EDIT its name and frequency, keeping its history, `s` to SKIP it today with a reason, or `p` to PAUSE it. `v` pauses everything for a VACATION.\n\
+        Press `[` and `]` or the arrows LEFT and RIGHT to go to PAST DAYS, where `d` sets an activity DONE or not.\n\
+        Press `u` to UNDO the last done, add, edit or removal and `Ctrl-r` to REDO it.\n\
             ",
-    ))
+    )
     .block(
         Block::default()
             .title("Y.A.c.H.T.")
@@ -30,10 +39,20 @@ pub fn render(app: &mut App, frame: &mut Frame) {
     .style(Style::default().fg(Color::White).bg(Color::Black))
     .alignment(Alignment::Left);
 
+    let list_title = match app.day_offset {
+        0 => String::from("TODAY'S ACTIVITIES"),
+        _ => format!(
+            "ACTIVITIES OF {} ([ ] TO CHANGE DAY)",
+            app.listed_date()
+                .format("%a %Y-%m-%d")
+                .to_string()
+                .to_uppercase()
+        ),
+    };
     let activities_list = List::new(app.todays_activities.clone())
         .block(
             Block::default()
-                .title("TODAY'S ACTIVITIES")
+                .title(list_title)
                 .title_style(Style::default().add_modifier(Modifier::BOLD))
                 .title_alignment(Alignment::Center)
                 .borders(Borders::ALL)
@@ -44,7 +63,12 @@ pub fn render(app: &mut App, frame: &mut Frame) {
         .highlight_symbol(">> ")
         .repeat_highlight_symbol(false);
 
-    let activities_status = List::new(app.activities_status.clone())
+    let statuses: Vec<String> = app
+        .activities_status
+        .iter()
+        .map(|s| fit_status(s))
+        .collect();
+    let activities_status = List::new(statuses)
         .block(
             Block::default()
                 .title_alignment(Alignment::Center)
@@ -55,8 +79,29 @@ pub fn render(app: &mut App, frame: &mut Frame) {
 
     let today_status_text = match app.day_status {
         true => String::from("A PERFECT DAY! (づ ◕‿◕ )づ"),
+        false if app.todays_activities.is_empty() && !app.paused_activities.is_empty() => {
+            String::from("A DAY OFF (￣o￣) zzZ")
+        }
         false => String::from("JUST A DAY └(・。・)┘"),
     };
+    let paused_text = match app.paused_activities.is_empty() {
+        true => String::new(),
+        false => format!("\nPaused: {}\n", app.paused_activities.join(", ")),
+    };
+    // the reasons are cut in the status column
+    let skipped: Vec<String> = app
+        .todays_activities
+        .iter()
+        .zip(&app.activities_status)
+        .filter_map(|(name, status)| {
+            let reason = status.strip_prefix("[~] ")?;
+            Some(format!("{} ({})", name, reason))
+        })
+        .collect();
+    let skipped_text = match skipped.is_empty() {
+        true => String::new(),
+        false => format!("\nSkipped: {}\n", skipped.join(", ")),
+    };
 
     let status_title = "STATUS".to_string() + " >>" + &app.status_day.to_uppercase() + "<< ";
 
@@ -66,8 +111,12 @@ pub fn render(app: &mut App, frame: &mut Frame) {
         {} activities remained till perfect day \n\
         \n\
         {} perfect days during last 30 days \n\
-        ",
-        today_status_text, app.activities_till_perfect_day, app.total_perfect_days,
+        {}{}",
+        today_status_text,
+        app.activities_till_perfect_day,
+        app.total_perfect_days,
+        paused_text,
+        skipped_text,
     ))
     .block(
         Block::default()
@@ -81,16 +130,51 @@ pub fn render(app: &mut App, frame: &mut Frame) {
     .alignment(Alignment::Left)
     .wrap(Wrap { trim: true });
 
-    let input_text: String;
-    match app.input_mode {
-        InputMode::ActiveName => input_text = format!("activity name: {}", app.input.as_str()),
+    let input_text = match app.input_mode {
+        InputMode::ActiveName => match app.new_activity_kind {
+            ActivityKind::Do => format!("activity name: {}", app.input.as_str()),
+            ActivityKind::Avoid => format!("activity to avoid: {}", app.input.as_str()),
+        },
         InputMode::ActiveFrequency => {
-            input_text = format!(
-                "AW for all week, WD for working days and WE for weekends: {}",
-                app.input.as_str()
-            )
+            format!("frequency ({}): {}", FREQUENCY_HELP, app.input.as_str())
         }
-        InputMode::Inactive => input_text = format!(""),
+        InputMode::ActiveTarget => format!(
+            "target (amount and unit like 2 KM, 8 GLASSES or 30 MIN, empty for a simple activity): {}",
+            app.input.as_str()
+        ),
+        InputMode::ActiveProgress => format!(
+            "amount done for {}: {}",
+            app.picked_activity,
+            app.input.as_str()
+        ),
+        InputMode::ActiveEditName => format!(
+            "new name of {}: {}",
+            app.picked_activity,
+            app.input.as_str()
+        ),
+        InputMode::ActiveEditFrequency => format!(
+            "new frequency of {} ({}): {}",
+            app.picked_activity,
+            FREQUENCY_HELP,
+            app.input.as_str()
+        ),
+        InputMode::ActiveSkip => format!(
+            "reason for skipping {} today, like RAIN or SICK: {}",
+            app.picked_activity,
+            app.input.as_str()
+        ),
+        InputMode::ActivePause => match app.picked_activity.is_empty() {
+            true => format!(
+                "pause everything (last day like 2024-01-27 or range like 2024-01-20..2024-01-27, empty to resume everything): {}",
+                app.input.as_str()
+            ),
+            false => format!(
+                "pause {} (last day like 2024-01-27 or range like 2024-01-20..2024-01-27): {}",
+                app.picked_activity,
+                app.input.as_str()
+            ),
+        },
+        InputMode::Inactive => String::new(),
     };
 
     let input = Paragraph::new(input_text)
@@ -136,9 +220,9 @@ pub fn render(app: &mut App, frame: &mut Frame) {
     };
 
     let status_part = Rect {
-        x: size.x + (list_field.width as f32 * 0.8) as u16,
+        x: size.x + (list_field.width as f32 * 0.7) as u16,
         y: size.y + split,
-        width: 5,
+        width: STATUS_WIDTH as u16,
         height: split,
     };
 
@@ -180,3 +264,11 @@ pub fn render(app: &mut App, frame: &mut Frame) {
     //dynamic, but based on other actions
     frame.render_widget(global_status, global_status_field);
 }
+
+/// Status cut to the width of the status column, ending with `…` when it doesn't fit.
+pub(crate) fn fit_status(status: &str) -> String {
+    match status.chars().count() > STATUS_WIDTH {
+        true => status.chars().take(STATUS_WIDTH - 1).chain(['…']).collect(),
+        false => status.to_string(),
+    }
+}